@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+
+use utils::ElementId;
+
+/// Contiguous storage for mesh elements, addressed by typed ids.
+///
+/// Removed slots are kept empty and reused by later insertions, so the id of
+/// an element stays valid for as long as the element is alive.
+///
+/// While a journal is open, the arena keeps the state each slot had before
+/// its first modification, so the modifications can be reverted.
+pub struct Arena<I: ElementId, T> {
+    slots: Vec<Option<T>>,
+    free: Vec<usize>,
+    len: usize,
+    journal: Option<HashMap<usize, Option<T>>>,
+    marker: PhantomData<I>,
+}
+
+impl<I: ElementId, T> Arena<I, T> {
+    pub fn new() -> Arena<I, T> {
+        Arena {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            journal: None,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Arena<I, T> {
+        Arena {
+            slots: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            len: 0,
+            journal: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Number of live elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of slots, live or not. Every valid id has an index below this.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn contains(&self, id: I) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn get(&self, id: I) -> Option<&T> {
+        match self.slots.get(id.index()) {
+            Some(Some(element)) => Some(element),
+            _ => None,
+        }
+    }
+
+    pub fn ids<'a>(&'a self) -> impl Iterator<Item = I> + 'a {
+        self.iter().map(|(id, _)| id)
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (I, &'a T)> + 'a {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.as_ref().map(|element| (I::new(i), element)))
+    }
+}
+
+impl<I: ElementId, T: Clone> Arena<I, T> {
+    pub fn insert(&mut self, element: T) -> I {
+        self.len += 1;
+        match self.free.pop() {
+            Some(index) => {
+                self.touch(index);
+                self.slots[index] = Some(element);
+                I::new(index)
+            }
+            None => {
+                self.touch(self.slots.len());
+                self.slots.push(Some(element));
+                I::new(self.slots.len() - 1)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: I) -> Option<T> {
+        self.touch(id.index());
+        let removed = match self.slots.get_mut(id.index()) {
+            Some(slot) => slot.take(),
+            None => None,
+        };
+        if removed.is_some() {
+            self.len -= 1;
+            self.free.push(id.index());
+        }
+        removed
+    }
+
+    pub fn get_mut(&mut self, id: I) -> Option<&mut T> {
+        self.touch(id.index());
+        match self.slots.get_mut(id.index()) {
+            Some(&mut Some(ref mut element)) => Some(element),
+            _ => None,
+        }
+    }
+
+    pub fn iter_mut<'a>(&'a mut self) -> impl Iterator<Item = (I, &'a mut T)> + 'a {
+        if self.journal.is_some() {
+            for index in 0..self.slots.len() {
+                self.touch(index);
+            }
+        }
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.as_mut().map(|element| (I::new(i), element)))
+    }
+
+    /// Starts recording the previous state of every slot modified from now on.
+    pub(crate) fn open_journal(&mut self) {
+        self.journal = Some(HashMap::new());
+    }
+
+    pub(crate) fn is_journal_open(&self) -> bool {
+        self.journal.is_some()
+    }
+
+    /// Stops recording and returns the state each modified slot had when the
+    /// journal was opened.
+    pub(crate) fn close_journal(&mut self) -> Vec<(I, Option<T>)> {
+        let mut entries: Vec<(I, Option<T>)> = self.journal.take()
+            .map_or_else(Vec::new, |journal| journal.into_iter().map(|(index, slot)| (I::new(index), slot)).collect());
+        entries.sort_by_key(|&(id, _)| id.index());
+        entries
+    }
+
+    /// Opens the journal again with the entries of a closed one.
+    pub(crate) fn resume_journal(&mut self, entries: Vec<(I, Option<T>)>) {
+        self.journal = Some(entries.into_iter().map(|(id, slot)| (id.index(), slot)).collect());
+    }
+
+    /// Puts `slot` back at `id`, returning what was there. Slots past the end
+    /// are created empty first.
+    pub(crate) fn replace_slot(&mut self, id: I, slot: Option<T>) -> Option<T> {
+        let index = id.index();
+        self.touch(index);
+        while self.slots.len() <= index {
+            self.free.push(self.slots.len());
+            self.slots.push(None);
+        }
+        match (self.slots[index].is_some(), slot.is_some()) {
+            (false, true) => {
+                self.len += 1;
+                self.free.retain(|&free| free != index);
+            }
+            (true, false) => {
+                self.len -= 1;
+                self.free.push(index);
+            }
+            _ => {}
+        }
+        ::std::mem::replace(&mut self.slots[index], slot)
+    }
+
+    // Saves the state of the slot at `index` if the journal is open. The slot
+    // may be the one about to be pushed, which is saved as empty.
+    #[inline]
+    fn touch(&mut self, index: usize) {
+        let journal = match self.journal {
+            Some(ref mut journal) => journal,
+            None => return,
+        };
+        let slots = &self.slots;
+        journal.entry(index).or_insert_with(|| slots.get(index).cloned().unwrap_or(None));
+    }
+}
+
+impl<I: ElementId, T> Default for Arena<I, T> {
+    fn default() -> Arena<I, T> {
+        Arena::new()
+    }
+}
+
+impl<I: ElementId, T> Index<I> for Arena<I, T> {
+    type Output = T;
+    fn index(&self, id: I) -> &T {
+        match self.get(id) {
+            Some(element) => element,
+            None => panic!("Accessing removed or out of bounds element {}.", id.index()),
+        }
+    }
+}
+
+impl<I: ElementId, T: Clone> IndexMut<I> for Arena<I, T> {
+    fn index_mut(&mut self, id: I) -> &mut T {
+        match self.get_mut(id) {
+            Some(element) => element,
+            None => panic!("Accessing removed or out of bounds element {}.", id.index()),
+        }
+    }
+}
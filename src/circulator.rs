@@ -0,0 +1,173 @@
+use arena::Arena;
+use halfedge::HalfEdge;
+use polyhedron::Polyhedron;
+use pos::Pos;
+use utils::{VertexId, HalfEdgeId, FacetId};
+
+/// Half-edges of a facet loop, following `next`.
+pub struct FacetHalfEdges<'a> {
+    edges: &'a Arena<HalfEdgeId, HalfEdge>,
+    start: Option<HalfEdgeId>,
+    current: Option<HalfEdgeId>,
+}
+
+impl<'a> FacetHalfEdges<'a> {
+    pub fn new(edges: &'a Arena<HalfEdgeId, HalfEdge>, start: Option<HalfEdgeId>) -> FacetHalfEdges<'a> {
+        FacetHalfEdges {
+            edges,
+            start,
+            current: start,
+        }
+    }
+}
+
+impl<'a> Iterator for FacetHalfEdges<'a> {
+    type Item = HalfEdgeId;
+
+    fn next(&mut self) -> Option<HalfEdgeId> {
+        let current = self.current?;
+        self.current = self.edges[current].next.filter(|next| Some(*next) != self.start);
+        Some(current)
+    }
+}
+
+/// Vertices of a facet loop, in the order of its half-edges.
+pub struct FacetVertices<'a> {
+    halfedges: FacetHalfEdges<'a>,
+}
+
+impl<'a> Iterator for FacetVertices<'a> {
+    type Item = VertexId;
+
+    fn next(&mut self) -> Option<VertexId> {
+        let edge = self.halfedges.next()?;
+        self.halfedges.edges[edge].vertex
+    }
+}
+
+/// Half-edges ending at a vertex, turning around it with `next` and `opposite`.
+///
+/// Border vertices need no special care since border edges have a twin
+/// with no face.
+pub struct VertexIncomingHalfEdges<'a> {
+    edges: &'a Arena<HalfEdgeId, HalfEdge>,
+    start: Option<HalfEdgeId>,
+    current: Option<HalfEdgeId>,
+}
+
+impl<'a> VertexIncomingHalfEdges<'a> {
+    pub fn new(edges: &'a Arena<HalfEdgeId, HalfEdge>, edge: Option<HalfEdgeId>) -> VertexIncomingHalfEdges<'a> {
+        VertexIncomingHalfEdges {
+            edges,
+            start: edge,
+            current: edge,
+        }
+    }
+
+    fn rotate(&self, edge: HalfEdgeId) -> Option<HalfEdgeId> {
+        let next = self.edges[edge].next?;
+        self.edges[next].opposite
+    }
+}
+
+impl<'a> Iterator for VertexIncomingHalfEdges<'a> {
+    type Item = HalfEdgeId;
+
+    fn next(&mut self) -> Option<HalfEdgeId> {
+        let current = self.current?;
+        self.current = self.rotate(current).filter(|next| Some(*next) != self.start);
+        Some(current)
+    }
+}
+
+/// Half-edges starting at a vertex.
+pub struct VertexOutgoingHalfEdges<'a> {
+    incoming: VertexIncomingHalfEdges<'a>,
+}
+
+impl<'a> Iterator for VertexOutgoingHalfEdges<'a> {
+    type Item = HalfEdgeId;
+
+    fn next(&mut self) -> Option<HalfEdgeId> {
+        let edge = self.incoming.next()?;
+        self.incoming.edges[edge].next
+    }
+}
+
+/// Vertices sharing an edge with a vertex.
+pub struct VertexNeighbors<'a> {
+    incoming: VertexIncomingHalfEdges<'a>,
+}
+
+impl<'a> Iterator for VertexNeighbors<'a> {
+    type Item = VertexId;
+
+    fn next(&mut self) -> Option<VertexId> {
+        let edge = self.incoming.next()?;
+        let next = self.incoming.edges[edge].next?;
+        self.incoming.edges[next].vertex
+    }
+}
+
+/// Facets around a vertex, leaving out the outside of the border.
+pub struct VertexFacets<'a> {
+    incoming: VertexIncomingHalfEdges<'a>,
+}
+
+impl<'a> Iterator for VertexFacets<'a> {
+    type Item = FacetId;
+
+    fn next(&mut self) -> Option<FacetId> {
+        loop {
+            let edge = self.incoming.next()?;
+            if let Some(face) = self.incoming.edges[edge].face {
+                return Some(face);
+            }
+        }
+    }
+}
+
+impl<T: Pos> Polyhedron<T> {
+    pub fn facet_halfedges(&self, facet: FacetId) -> FacetHalfEdges<'_> {
+        FacetHalfEdges::new(&self.edges, self.facets[facet].edge)
+    }
+
+    pub fn facet_vertices(&self, facet: FacetId) -> FacetVertices<'_> {
+        FacetVertices { halfedges: self.facet_halfedges(facet) }
+    }
+
+    /// Half-edges of the loop going through `edge`.
+    pub fn loop_halfedges(&self, edge: HalfEdgeId) -> FacetHalfEdges<'_> {
+        FacetHalfEdges::new(&self.edges, Some(edge))
+    }
+
+    /// Vertices of the loop going through `edge`.
+    pub fn loop_vertices(&self, edge: HalfEdgeId) -> FacetVertices<'_> {
+        FacetVertices { halfedges: self.loop_halfedges(edge) }
+    }
+
+    pub fn vertex_incoming_halfedges(&self, vertex: VertexId) -> VertexIncomingHalfEdges<'_> {
+        VertexIncomingHalfEdges::new(&self.edges, self.vertices[vertex].edge)
+    }
+
+    pub fn vertex_outgoing_halfedges(&self, vertex: VertexId) -> VertexOutgoingHalfEdges<'_> {
+        VertexOutgoingHalfEdges { incoming: self.vertex_incoming_halfedges(vertex) }
+    }
+
+    pub fn vertex_neighbors(&self, vertex: VertexId) -> VertexNeighbors<'_> {
+        VertexNeighbors { incoming: self.vertex_incoming_halfedges(vertex) }
+    }
+
+    pub fn vertex_facets(&self, vertex: VertexId) -> VertexFacets<'_> {
+        VertexFacets { incoming: self.vertex_incoming_halfedges(vertex) }
+    }
+
+    pub fn is_border_vertex(&self, vertex: VertexId) -> bool {
+        self.vertex_incoming_halfedges(vertex).any(|edge| self.edges[edge].is_border())
+    }
+
+    /// True if the edge or its opposite has no face.
+    pub fn is_border_edge(&self, edge: HalfEdgeId) -> bool {
+        self.edges[edge].is_border() || self.edges[edge].opposite.is_none_or(|opposite| self.edges[opposite].is_border())
+    }
+}
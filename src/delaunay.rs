@@ -0,0 +1,65 @@
+use error::HalfEdgeError;
+use polyhedron::Polyhedron2;
+use predicates::{incircle, orient2d, Sign};
+use utils::*;
+
+// Delaunay triangulation by Lawson's flips: an edge whose opposite apex lies
+// strictly inside the circumcircle of its triangle is flipped, and the edges
+// around the flipped quadrilateral are checked again. Each flip raises the
+// triangulation on the lifting paraboloid, so the loop ends. Cocircular
+// points are left as they are, and facets that are not triangles are kept.
+
+impl Polyhedron2 {
+    /// False if `edge` lies between two triangles and the apex across it is
+    /// inside the circumcircle of its triangle.
+    pub fn is_locally_delaunay(&self, edge: HalfEdgeId) -> bool {
+        if !self.is_flippable(edge) {
+            return true;
+        }
+        let opposite_edge = get_element!(self.edges[edge], opposite);
+        let position = |e: HalfEdgeId| self.vertices[get_element!(self.edges[e], vertex)].position;
+        let a = position(opposite_edge);
+        let b = position(edge);
+        let c = position(get_element!(self.edges[edge], next));
+        let d = position(get_element!(self.edges[opposite_edge], next));
+
+        // The in-circle sign is relative to the orientation of the triangle,
+        // so that clockwise meshes work as well.
+        let orientation = orient2d(a, b, c);
+        orientation == Sign::Zero || incircle(a, b, c, d) != orientation
+    }
+
+    /// Flips edges until every edge is locally Delaunay, and returns the
+    /// number of flips. Recorded as a single change.
+    pub fn make_delaunay(&mut self) -> usize {
+        self.try_make_delaunay().unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_make_delaunay(&mut self) -> Result<usize, HalfEdgeError> {
+        self.record(|poly| poly.apply_make_delaunay())
+    }
+
+    fn apply_make_delaunay(&mut self) -> Result<usize, HalfEdgeError> {
+        let mut pending: Vec<HalfEdgeId> = self.edges.iter()
+            .filter(|&(_, edge)| !edge.is_border())
+            .map(|(id, _)| id)
+            .collect();
+
+        let mut flips = 0;
+        while let Some(edge) = pending.pop() {
+            if !self.edges.contains(edge) || self.is_locally_delaunay(edge) || !self.is_flippable_convex(edge) {
+                continue;
+            }
+            self.apply_flip_edge(edge)?;
+            flips += 1;
+
+            let opposite_edge = get_element!(self.edges[edge], opposite);
+            for &side in &[edge, opposite_edge] {
+                let next_edge = get_element!(self.edges[side], next);
+                pending.push(next_edge);
+                pending.push(get_element!(self.edges[next_edge], next));
+            }
+        }
+        Ok(flips)
+    }
+}
@@ -0,0 +1,319 @@
+use std::io;
+
+use svg;
+use svg::Document;
+use svg::node;
+use svg::node::element::{Circle, Group, Path, Text};
+use svg::node::element::path::Data;
+
+use polyhedron::Polyhedron2;
+use pos::{Pos, Pos2};
+use utils::*;
+
+/// Settings for `Polyhedron2::draw_svg_with`.
+pub struct SvgOptions {
+    /// Size of the picture, in pixels.
+    pub width: u32,
+    pub height: u32,
+    /// Space left around the mesh, as a fraction of its bounding box size.
+    pub margin: f32,
+    /// Puts the y axis upwards, as in usual mesh coordinates.
+    pub flip_y: bool,
+    pub fill: Box<dyn Fn(FacetId) -> String>,
+    pub stroke: Box<dyn Fn(FacetId) -> String>,
+    /// Stroke width in pixels, kept the same whatever the scale of the mesh.
+    pub stroke_width: f32,
+    /// Radius of the dot drawn on each vertex, in mesh units. No dots if `None`.
+    pub vertex_radius: Option<f32>,
+    pub vertex_color: String,
+}
+
+impl Default for SvgOptions {
+    fn default() -> SvgOptions {
+        SvgOptions {
+            width: 500,
+            height: 500,
+            margin: 0.05,
+            flip_y: false,
+            fill: Box::new(|_| "black".to_string()),
+            stroke: Box::new(|_| "red".to_string()),
+            stroke_width: 3.,
+            vertex_radius: None,
+            vertex_color: "blue".to_string(),
+        }
+    }
+}
+
+impl SvgOptions {
+    /// Light facets and thin strokes, so the overlay of `draw_debug_svg` stays readable.
+    pub fn debug() -> SvgOptions {
+        SvgOptions {
+            width: 1000,
+            height: 1000,
+            margin: 0.1,
+            fill: Box::new(|_| "#f0f0f0".to_string()),
+            stroke: Box::new(|_| "#a0a0a0".to_string()),
+            stroke_width: 1.,
+            ..SvgOptions::default()
+        }
+    }
+}
+
+/// Area of the plane shown in the picture.
+pub(crate) struct ViewBox {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub width: f32,
+    pub height: f32,
+    flip: Option<f32>,
+}
+
+impl ViewBox {
+    /// Bounding box of the mesh grown by `margin`, or the unit square for an empty mesh.
+    pub fn new(poly: &Polyhedron2, margin: f32, flip_y: bool) -> ViewBox {
+        let mut positions = poly.vertices.iter().map(|(_, vertex)| vertex.position);
+        let (mut min, mut max) = match positions.next() {
+            Some(first) => (first, first),
+            None => (Pos2 { x: 0., y: 0. }, Pos2 { x: 1., y: 1. }),
+        };
+        for p in positions {
+            min = Pos2 { x: min.x.min(p.x), y: min.y.min(p.y) };
+            max = Pos2 { x: max.x.max(p.x), y: max.y.max(p.y) };
+        }
+
+        // A flat mesh still needs a visible area.
+        let size = (max.x - min.x).max(max.y - min.y).max(1e-6);
+        let width = (max.x - min.x).max(size * 1e-3);
+        let height = (max.y - min.y).max(size * 1e-3);
+        ViewBox {
+            min_x: min.x - width * margin,
+            min_y: min.y - height * margin,
+            width: width * (1. + 2. * margin),
+            height: height * (1. + 2. * margin),
+            flip: if flip_y { Some(min.y + max.y) } else { None },
+        }
+    }
+
+    /// Length of one pixel in mesh units.
+    pub fn pixel(&self, width: u32, height: u32) -> f32 {
+        (self.width / width.max(1) as f32).max(self.height / height.max(1) as f32)
+    }
+
+    /// Position in the picture of a point of the mesh.
+    pub fn map(&self, p: Pos2) -> (f32, f32) {
+        match self.flip {
+            Some(sum) => (p.x, sum - p.y),
+            None => (p.x, p.y),
+        }
+    }
+
+    pub fn document(&self, width: u32, height: u32) -> Document {
+        Document::new()
+            .set("width", width)
+            .set("height", height)
+            .set("viewBox", (self.min_x, self.min_y, self.width, self.height))
+    }
+}
+
+impl Polyhedron2 {
+    pub fn to_svg_document(&self, options: &SvgOptions) -> Document {
+        let view = ViewBox::new(self, options.margin, options.flip_y);
+        let mut document = view.document(options.width, options.height);
+
+        for (f, facet) in self.facets.iter() {
+            // Each loop is a subpath, so the even-odd rule leaves holes empty.
+            let mut face_data = Data::new();
+            for start in facet.edge.iter().chain(facet.rings.iter()) {
+                let mut vertices = self.loop_vertices(*start).take(self.edges.len());
+                let start_vertex = match vertices.next() {
+                    Some(vertex) => vertex,
+                    None => continue,
+                };
+                face_data = face_data.move_to(view.map(self.vertices[start_vertex].position));
+                for vertex in vertices {
+                    face_data = face_data.line_to(view.map(self.vertices[vertex].position));
+                }
+                face_data = face_data.close();
+            }
+            if face_data.is_empty() {
+                continue;
+            }
+
+            let face_path = Path::new()
+                .set("fill", (options.fill)(f))
+                .set("fill-rule", "evenodd")
+                .set("stroke", (options.stroke)(f))
+                .set("stroke-width", options.stroke_width)
+                .set("vector-effect", "non-scaling-stroke")
+                .set("d", face_data);
+            document = document.add(face_path);
+        }
+
+        if let Some(radius) = options.vertex_radius {
+            for (_, vertex) in self.vertices.iter() {
+                let (x, y) = view.map(vertex.position);
+                let dot = Circle::new()
+                    .set("cx", x)
+                    .set("cy", y)
+                    .set("r", radius)
+                    .set("fill", options.vertex_color.as_str());
+                document = document.add(dot);
+            }
+        }
+        document
+    }
+
+    /// Draws the facets as `to_svg_document`, then each half-edge as an arrow
+    /// shifted towards its facet, with the index of every element.
+    ///
+    /// Both half-edges of a pair share a colour. Border half-edges are dashed
+    /// and the border itself is underlined in yellow.
+    pub fn to_debug_svg_document(&self, options: &SvgOptions) -> Document {
+        let view = ViewBox::new(self, options.margin, options.flip_y);
+        let pixel = view.pixel(options.width, options.height);
+        let font_size = 12. * pixel;
+        let document = self.to_svg_document(options);
+
+        // Origins come from the previous edge, since `opposite` may be the broken link.
+        let mut origins = vec![None; self.edges.capacity()];
+        for (_, edge) in self.edges.iter() {
+            if let Some(next) = edge.next.filter(|next| self.edges.contains(*next)) {
+                origins[next.index()] = edge.vertex;
+            }
+        }
+        for (id, edge) in self.edges.iter() {
+            if origins[id.index()].is_none() {
+                origins[id.index()] = edge.opposite.and_then(|opposite| self.edges.get(opposite)).and_then(|opposite| opposite.vertex);
+            }
+        }
+
+        let mut centers = vec![None; self.facets.capacity()];
+        for (id, facet) in self.facets.iter() {
+            let mut sum = Pos2 { x: 0., y: 0. };
+            let mut count = 0;
+            let mut current = facet.edge;
+            while let Some(edge) = current.and_then(|edge| self.edges.get(edge)) {
+                if let Some(vertex) = edge.vertex.and_then(|vertex| self.vertices.get(vertex)) {
+                    sum = sum + vertex.position;
+                    count += 1;
+                }
+                current = edge.next.filter(|next| Some(*next) != facet.edge);
+                if count > self.edges.len() {
+                    break;
+                }
+            }
+            if count > 0 {
+                centers[id.index()] = Some(sum / count as f32);
+            }
+        }
+
+        let mut borders = Group::new()
+            .set("stroke", "#ffd700")
+            .set("stroke-width", 7.)
+            .set("stroke-linecap", "round")
+            .set("vector-effect", "non-scaling-stroke");
+        let mut arrows = Group::new()
+            .set("fill", "none")
+            .set("stroke-width", 1.5)
+            .set("vector-effect", "non-scaling-stroke");
+        let mut labels = Group::new()
+            .set("font-family", "monospace")
+            .set("font-size", font_size)
+            .set("text-anchor", "middle")
+            .set("dominant-baseline", "central");
+
+        for (id, edge) in self.edges.iter() {
+            let target = match edge.vertex.and_then(|vertex| self.vertices.get(vertex)) {
+                Some(vertex) => vertex.position,
+                None => continue,
+            };
+            let origin = match origins[id.index()].and_then(|vertex| self.vertices.get(vertex)) {
+                Some(vertex) => vertex.position,
+                None => continue,
+            };
+            let direction = target - origin;
+            let length = direction.norm();
+            if length == 0. {
+                continue;
+            }
+            let direction = direction / length;
+            let mut normal = Pos2 { x: -direction.y, y: direction.x };
+
+            // Shift towards the facet of the edge, or away from the facet of its twin.
+            let opposite_face = edge.opposite.and_then(|opposite| self.edges.get(opposite)).and_then(|opposite| opposite.face);
+            let side = match (edge.face, opposite_face) {
+                (Some(face), _) => centers.get(face.index()).and_then(|c| *c).map(|center| (center - origin).dot(&normal)),
+                (None, Some(face)) => centers.get(face.index()).and_then(|c| *c).map(|center| -(center - origin).dot(&normal)),
+                (None, None) => None,
+            };
+            if side.is_some_and(|side| side < 0.) {
+                normal = normal * -1.;
+            }
+
+            let shift = normal * (6. * pixel).min(0.1 * length);
+            let trim = direction * (0.15 * length).min(12. * pixel).max(0.05 * length);
+            let start = origin + trim + shift;
+            let end = target - trim + shift;
+            let head = (0.2 * length).min(8. * pixel);
+            let wing = direction * -head;
+            let left = end + wing + normal * (0.5 * head);
+            let right = end + wing - normal * (0.5 * head);
+            let data = Data::new()
+                .move_to(view.map(start))
+                .line_to(view.map(end))
+                .move_to(view.map(left))
+                .line_to(view.map(end))
+                .line_to(view.map(right));
+
+            let pair = edge.opposite.map_or(id.index(), |opposite| id.index().min(opposite.index()));
+            let mut arrow = Path::new()
+                .set("stroke", format!("hsl({}, 80%, 40%)", pair * 137 % 360))
+                .set("d", data);
+            if edge.is_border() {
+                arrow = arrow.set("stroke-dasharray", "4 2");
+                let (x1, y1) = view.map(origin);
+                let (x2, y2) = view.map(target);
+                borders = borders.add(Path::new().set("d", Data::new().move_to((x1, y1)).line_to((x2, y2))));
+            }
+            arrows = arrows.add(arrow);
+
+            let (x, y) = view.map((start + end) * 0.5 + normal * font_size);
+            labels = labels.add(label(x, y, format!("e{}", id.index()), "#303030"));
+        }
+
+        for (id, vertex) in self.vertices.iter() {
+            let (x, y) = view.map(vertex.position);
+            labels = labels.add(label(x, y - font_size, format!("v{}", id.index()), "blue"));
+        }
+        for (id, center) in centers.iter().enumerate() {
+            if let Some(center) = *center {
+                let (x, y) = view.map(center);
+                labels = labels.add(label(x, y, format!("f{}", id), "green"));
+            }
+        }
+
+        document.add(borders).add(arrows).add(labels)
+    }
+
+    pub fn draw_debug_svg(&self, path: &str, options: &SvgOptions) -> io::Result<()> {
+        svg::save(path, &self.to_debug_svg_document(options))
+    }
+
+    pub fn draw_svg_with(&self, path: &str, options: &SvgOptions) -> io::Result<()> {
+        svg::save(path, &self.to_svg_document(options))
+    }
+
+    /// Draws the facets with the default style, the view fitted to the mesh.
+    pub fn draw_svg(&self, path: &str, height: u32, width: u32) -> io::Result<()> {
+        let options = SvgOptions { width, height, ..SvgOptions::default() };
+        self.draw_svg_with(path, &options)
+    }
+}
+
+fn label(x: f32, y: f32, content: String, color: &str) -> Text {
+    Text::new()
+        .set("x", x)
+        .set("y", y)
+        .set("fill", color)
+        .add(node::Text::new(content))
+}
@@ -0,0 +1,147 @@
+use std::error::Error;
+use std::fmt;
+
+use utils::{VertexId, HalfEdgeId, FacetId};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Element {
+    Vertex(VertexId),
+    HalfEdge(HalfEdgeId),
+    Facet(FacetId),
+}
+
+impl From<VertexId> for Element {
+    fn from(id: VertexId) -> Element {
+        Element::Vertex(id)
+    }
+}
+
+impl From<HalfEdgeId> for Element {
+    fn from(id: HalfEdgeId) -> Element {
+        Element::HalfEdge(id)
+    }
+}
+
+impl From<FacetId> for Element {
+    fn from(id: FacetId) -> Element {
+        Element::Facet(id)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HalfEdgeError {
+    /// The id does not refer to a live element of the mesh.
+    RemovedElement(Element),
+    MissingNext(HalfEdgeId),
+    MissingOpposite(HalfEdgeId),
+    MissingVertex(HalfEdgeId),
+    MissingFace(HalfEdgeId),
+    /// A vertex or a facet has no associated half-edge.
+    MissingEdge(Element),
+    /// Following `next` from this half-edge never comes back to it.
+    BrokenCycle(HalfEdgeId),
+    BoundaryVertex(VertexId),
+    /// The edge or its opposite lies on the border and has no face.
+    BoundaryEdge(HalfEdgeId),
+    NotTriangle(FacetId),
+    FacetMismatch(FacetId, FacetId),
+    VertexMismatch(VertexId, VertexId),
+    /// Collapsing the edge would make the mesh non-manifold.
+    NotCollapsible(HalfEdgeId),
+    /// The two vertices the edge would join after a flip already share an
+    /// edge, or, in a planar mesh, the flip would invert or flatten a triangle.
+    NotFlippable(HalfEdgeId),
+    /// The split parameter is not a number from 0 to 1.
+    InvalidSplit(HalfEdgeId),
+    /// The element still has edges.
+    NotIsolated(Element),
+    /// The two edges lie on the same loop.
+    SameLoop(HalfEdgeId, HalfEdgeId),
+    /// The two edges lie on different loops of the facet.
+    DifferentLoops(HalfEdgeId, HalfEdgeId),
+    /// The operator needs two different edges.
+    SameEdge(HalfEdgeId),
+    /// The edge and its opposite do not split a loop into two loops with edges.
+    NotBridge(HalfEdgeId),
+    /// The edge has the same facet on both sides.
+    SameFacet(FacetId),
+    /// A facet needs at least three different vertices.
+    DegenerateFacet,
+    /// The vertex is not on the border, so no facet can be added at it.
+    InteriorVertex(VertexId),
+    /// The edge already has a facet.
+    InteriorEdge(HalfEdgeId),
+    /// The facets around the vertex leave no gap for the new facet.
+    NonManifoldVertex(VertexId),
+}
+
+impl fmt::Display for HalfEdgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HalfEdgeError::RemovedElement(element) => write!(f, "{:?} is not part of the mesh", element),
+            HalfEdgeError::MissingNext(edge) => write!(f, "{:?} has no next edge", edge),
+            HalfEdgeError::MissingOpposite(edge) => write!(f, "{:?} has no opposite edge", edge),
+            HalfEdgeError::MissingVertex(edge) => write!(f, "{:?} has no vertex", edge),
+            HalfEdgeError::MissingFace(edge) => write!(f, "{:?} has no face", edge),
+            HalfEdgeError::MissingEdge(element) => write!(f, "{:?} has no associated edge", element),
+            HalfEdgeError::BrokenCycle(edge) => write!(f, "the next cycle of {:?} does not close", edge),
+            HalfEdgeError::BoundaryVertex(vertex) => write!(f, "{:?} is a border vertex", vertex),
+            HalfEdgeError::BoundaryEdge(edge) => write!(f, "{:?} is a border edge", edge),
+            HalfEdgeError::NotTriangle(facet) => write!(f, "{:?} is not a triangle", facet),
+            HalfEdgeError::FacetMismatch(f1, f2) => write!(f, "edges belong to different facets {:?} and {:?}", f1, f2),
+            HalfEdgeError::VertexMismatch(v1, v2) => write!(f, "edges point to different vertices {:?} and {:?}", v1, v2),
+            HalfEdgeError::NotCollapsible(edge) => write!(f, "collapsing {:?} would break the mesh", edge),
+            HalfEdgeError::NotFlippable(edge) => write!(f, "flipping {:?} would duplicate an edge or invert a triangle", edge),
+            HalfEdgeError::InvalidSplit(edge) => write!(f, "{:?} can only be split between its ends", edge),
+            HalfEdgeError::NotIsolated(element) => write!(f, "{:?} still has edges", element),
+            HalfEdgeError::SameLoop(e1, e2) => write!(f, "{:?} and {:?} are on the same loop", e1, e2),
+            HalfEdgeError::DifferentLoops(e1, e2) => write!(f, "{:?} and {:?} are on different loops", e1, e2),
+            HalfEdgeError::SameEdge(edge) => write!(f, "{:?} is given twice", edge),
+            HalfEdgeError::NotBridge(edge) => write!(f, "{:?} does not join two loops of a facet", edge),
+            HalfEdgeError::SameFacet(facet) => write!(f, "both sides of the edge belong to {:?}", facet),
+            HalfEdgeError::DegenerateFacet => write!(f, "a facet needs at least three different vertices"),
+            HalfEdgeError::InteriorVertex(vertex) => write!(f, "{:?} is not on the border", vertex),
+            HalfEdgeError::InteriorEdge(edge) => write!(f, "{:?} already has a facet", edge),
+            HalfEdgeError::NonManifoldVertex(vertex) => write!(f, "no gap is left around {:?} for a new facet", vertex),
+        }
+    }
+}
+
+impl Error for HalfEdgeError {
+    fn description(&self) -> &str {
+        "invalid half-edge structure"
+    }
+}
+
+/// Problems found while building a mesh from an indexed face list.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MeshBuildError {
+    /// Face `face` refers to a vertex index past the end of the positions.
+    IndexOutOfBounds { face: usize, index: usize },
+    /// Face with less than three vertices, or using a vertex twice.
+    DegenerateFace(usize),
+    /// More than two faces share the edge between these vertices.
+    NonManifoldEdge(usize, usize),
+    /// Two faces go along the edge between these vertices in the same direction.
+    InconsistentOrientation(usize, usize),
+    /// The faces around this vertex do not form a single fan.
+    NonManifoldVertex(usize),
+}
+
+impl fmt::Display for MeshBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MeshBuildError::IndexOutOfBounds { face, index } => write!(f, "face {} uses vertex {} which does not exist", face, index),
+            MeshBuildError::DegenerateFace(face) => write!(f, "face {} is degenerate", face),
+            MeshBuildError::NonManifoldEdge(a, b) => write!(f, "edge {}-{} is shared by more than two faces", a, b),
+            MeshBuildError::InconsistentOrientation(a, b) => write!(f, "faces around edge {}-{} have opposite orientations", a, b),
+            MeshBuildError::NonManifoldVertex(v) => write!(f, "vertex {} is not manifold", v),
+        }
+    }
+}
+
+impl Error for MeshBuildError {
+    fn description(&self) -> &str {
+        "invalid indexed face list"
+    }
+}
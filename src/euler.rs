@@ -0,0 +1,671 @@
+use std::collections::HashMap;
+
+use error::HalfEdgeError;
+use facet::Facet;
+use halfedge::HalfEdge;
+use polyhedron::Polyhedron;
+use pos::Pos;
+use utils::*;
+use vertex::Vertex;
+
+/// Terms of the Euler-Poincaré formula `V - E + F - R = 2(S - G) - B`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EulerCounts {
+    pub vertices: usize,
+    /// Pairs of opposite half-edges.
+    pub edges: usize,
+    pub facets: usize,
+    /// Loops of a facet besides its first one.
+    pub rings: usize,
+    /// Connected pieces of the mesh, the rings of a facet being connected to it.
+    pub shells: usize,
+    /// Loops of border half-edges.
+    pub border_loops: usize,
+}
+
+impl EulerCounts {
+    /// `V - E + F - R`.
+    pub fn characteristic(&self) -> i64 {
+        self.vertices as i64 - self.edges as i64 + self.facets as i64 - self.rings as i64
+    }
+
+    /// Total genus of the shells, or `None` if the counts do not fit the formula.
+    pub fn genus(&self) -> Option<i64> {
+        let twice_genus = 2 * self.shells as i64 - self.border_loops as i64 - self.characteristic();
+        if twice_genus < 0 || twice_genus % 2 != 0 {
+            return None;
+        }
+        Some(twice_genus / 2)
+    }
+}
+
+fn find_root(parents: &mut [usize], index: usize) -> usize {
+    let mut root = index;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    let mut current = index;
+    while parents[current] != root {
+        let parent = parents[current];
+        parents[current] = root;
+        current = parent;
+    }
+    root
+}
+
+impl<T: Pos> Polyhedron<T> {
+    /// Counts the terms of the Euler-Poincaré formula on a valid mesh.
+    pub fn euler_counts(&self) -> EulerCounts {
+        let mut counts = EulerCounts {
+            vertices: self.vertices_size(),
+            edges: self.edges_size() / 2,
+            facets: self.facets_size(),
+            ..EulerCounts::default()
+        };
+
+        let mut parents: Vec<usize> = (0..self.vertices.capacity()).collect();
+        let mut facet_loops = vec![0usize; self.facets.capacity()];
+        let mut facet_vertex: Vec<Option<VertexId>> = vec![None; self.facets.capacity()];
+        let mut visited = vec![false; self.edges.capacity()];
+
+        for start in self.edges.ids() {
+            if visited[start.index()] {
+                continue;
+            }
+            let first_vertex = get_element!(self.edges[start], vertex);
+            for edge in self.loop_halfedges(start) {
+                visited[edge.index()] = true;
+                let vertex = get_element!(self.edges[edge], vertex);
+                let (a, b) = (find_root(&mut parents, vertex.index()), find_root(&mut parents, first_vertex.index()));
+                parents[a] = b;
+            }
+            match self.edges[start].face {
+                Some(face) => {
+                    facet_loops[face.index()] += 1;
+                    match facet_vertex[face.index()] {
+                        Some(vertex) => {
+                            let (a, b) = (find_root(&mut parents, vertex.index()), find_root(&mut parents, first_vertex.index()));
+                            parents[a] = b;
+                        }
+                        None => facet_vertex[face.index()] = Some(first_vertex),
+                    }
+                }
+                None => counts.border_loops += 1,
+            }
+        }
+
+        counts.rings = facet_loops.iter().filter(|loops| **loops > 1).map(|loops| loops - 1).sum();
+        for vertex in self.vertices.ids() {
+            if find_root(&mut parents, vertex.index()) == vertex.index() {
+                counts.shells += 1;
+            }
+        }
+        counts
+    }
+}
+
+// Euler operators on shells, rings and whole facets
+
+impl<T: Pos> Polyhedron<T> {
+    /// MVFS: starts a new shell with a single vertex and an empty facet.
+    pub fn make_vertex_facet_shell(&mut self, position: T) -> (VertexId, FacetId) {
+        let result = self.record(|poly| {
+            let vertex = poly.vertices.insert(Vertex { position, edge: None });
+            let facet = poly.facets.insert(Facet::new());
+            Ok((vertex, facet))
+        });
+        result.unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn kill_vertex_facet_shell(&mut self, vertex: VertexId, facet: FacetId) {
+        self.try_kill_vertex_facet_shell(vertex, facet).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// KVFS: removes a shell left with a single vertex and an empty facet.
+    pub fn try_kill_vertex_facet_shell(&mut self, vertex: VertexId, facet: FacetId) -> Result<(), HalfEdgeError> {
+        self.record(|poly| poly.apply_kill_vertex_facet_shell(vertex, facet))
+    }
+
+    fn apply_kill_vertex_facet_shell(&mut self, vertex: VertexId, facet: FacetId) -> Result<(), HalfEdgeError> {
+        self.try_isolated(vertex, facet)?;
+        self.discard_vertex(vertex);
+        self.discard_facet(facet);
+        Ok(())
+    }
+
+    fn try_isolated(&self, vertex: VertexId, facet: FacetId) -> Result<(), HalfEdgeError> {
+        let vertex_edge = self.vertices.get(vertex).ok_or(HalfEdgeError::RemovedElement(vertex.into()))?.edge;
+        let facet_edge = self.facets.get(facet).ok_or(HalfEdgeError::RemovedElement(facet.into()))?.edge;
+        if vertex_edge.is_some() {
+            return Err(HalfEdgeError::NotIsolated(vertex.into()));
+        }
+        if facet_edge.is_some() {
+            return Err(HalfEdgeError::NotIsolated(facet.into()));
+        }
+        Ok(())
+    }
+
+    // True if the gap after the border edge `border_previous`, at its vertex,
+    // is bounded by border coming from `previous` or going to `next`, that is
+    // if they lie on the parts of the border loop on each side of the gap
+    // before it comes back to the vertex.
+    fn try_gap_bounded_by(&self, border_previous: HalfEdgeId, previous: VertexId, next: VertexId) -> Result<bool, HalfEdgeError> {
+        let mut ends = Vec::new();
+        for edge in self.try_cycle(border_previous)? {
+            ends.push(self.try_vertex(edge)?);
+        }
+        let vertex = ends[0];
+        let after = ends[1..].iter().position(|v| *v == vertex).map_or(ends.len(), |k| k + 1);
+        let before = ends.iter().rposition(|v| *v == vertex).unwrap_or(0);
+        Ok(ends[before + 1..].contains(&previous) || ends[1..after].contains(&next))
+    }
+
+    pub fn make_first_edge_vertex(&mut self, vertex: VertexId, facet: FacetId, position: T) -> VertexId {
+        self.try_make_first_edge_vertex(vertex, facet, position).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// MEV on a shell made by `make_vertex_facet_shell`: adds a vertex at
+    /// `position` and an edge to it, with `facet` on both sides.
+    pub fn try_make_first_edge_vertex(&mut self, vertex: VertexId, facet: FacetId, position: T) -> Result<VertexId, HalfEdgeError> {
+        self.record(|poly| poly.apply_make_first_edge_vertex(vertex, facet, position))
+    }
+
+    fn apply_make_first_edge_vertex(&mut self, vertex: VertexId, facet: FacetId, position: T) -> Result<VertexId, HalfEdgeError> {
+        self.try_isolated(vertex, facet)?;
+
+        let new_vertex = self.vertices.insert(Vertex { position, edge: None });
+        let edge = self.edges.insert(HalfEdge {
+            vertex: Some(new_vertex),
+            face: Some(facet),
+            opposite: None,
+            next: None,
+        });
+        let opposite_edge = self.edges.insert(HalfEdge {
+            vertex: Some(vertex),
+            face: Some(facet),
+            opposite: Some(edge),
+            next: Some(edge),
+        });
+        self.edges[edge].opposite = Some(opposite_edge);
+        self.edges[edge].next = Some(opposite_edge);
+
+        self.vertices[new_vertex].edge = Some(edge);
+        self.vertices[vertex].edge = Some(opposite_edge);
+        self.facets[facet].edge = Some(edge);
+
+        self.vertex_properties.interpolate(new_vertex, &[(vertex, 1.)]);
+
+        Ok(new_vertex)
+    }
+
+    pub fn make_edge_vertex(&mut self, edge: HalfEdgeId, position: T) -> VertexId {
+        self.try_make_edge_vertex(edge, position).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// MEV: adds a vertex at `position` and an edge to it from the vertex of
+    /// `edge`, inside the facet of `edge` and right after it in its loop.
+    pub fn try_make_edge_vertex(&mut self, edge: HalfEdgeId, position: T) -> Result<VertexId, HalfEdgeError> {
+        self.record(|poly| poly.apply_make_edge_vertex(edge, position))
+    }
+
+    fn apply_make_edge_vertex(&mut self, edge: HalfEdgeId, position: T) -> Result<VertexId, HalfEdgeError> {
+        let face = self.try_face(edge)?;
+        let vertex = self.try_vertex(edge)?;
+        let next_edge = try_element!(self.edges, edge, next, MissingNext)?;
+
+        let new_vertex = self.vertices.insert(Vertex { position, edge: None });
+        let new_edge = self.edges.insert(HalfEdge {
+            vertex: Some(new_vertex),
+            face: Some(face),
+            opposite: None,
+            next: None,
+        });
+        let new_opposite_edge = self.edges.insert(HalfEdge {
+            vertex: Some(vertex),
+            face: Some(face),
+            opposite: Some(new_edge),
+            next: Some(next_edge),
+        });
+        self.edges[new_edge].opposite = Some(new_opposite_edge);
+        self.edges[new_edge].next = Some(new_opposite_edge);
+        self.edges[edge].next = Some(new_edge);
+
+        self.vertices[new_vertex].edge = Some(new_edge);
+
+        // The new vertex and corners copy the vertex they hang from.
+        self.vertex_properties.interpolate(new_vertex, &[(vertex, 1.)]);
+        self.corner_properties.interpolate(new_edge, &[(edge, 1.)]);
+        self.corner_properties.interpolate(new_opposite_edge, &[(edge, 1.)]);
+
+        Ok(new_vertex)
+    }
+
+    pub fn kill_edge_vertex(&mut self, edge: HalfEdgeId) {
+        self.try_kill_edge_vertex(edge).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// KEV: removes the vertex of `edge` along with `edge`, the vertex having
+    /// no other edge. The last edge of a shell leaves its facet empty.
+    pub fn try_kill_edge_vertex(&mut self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        self.record(|poly| poly.apply_kill_edge_vertex(edge))
+    }
+
+    fn apply_kill_edge_vertex(&mut self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        let opposite_edge = try_element!(self.edges, edge, opposite, MissingOpposite)?;
+        let vertex = self.try_vertex(edge)?;
+        let opposite_vertex = self.try_vertex(opposite_edge)?;
+        if try_element!(self.edges, edge, next, MissingNext)? != opposite_edge {
+            return Err(HalfEdgeError::NotIsolated(vertex.into()));
+        }
+        let cycle = self.try_cycle(edge)?;
+        let previous_edge = cycle[cycle.len() - 1];
+        let next_opposite_edge = get_element!(self.edges[opposite_edge], next);
+        let face = self.edges[edge].face;
+
+        if previous_edge == opposite_edge {
+            self.vertices[opposite_vertex].edge = None;
+        } else {
+            self.edges[previous_edge].next = Some(next_opposite_edge);
+            if self.vertices[opposite_vertex].edge == Some(opposite_edge) {
+                self.vertices[opposite_vertex].edge = Some(previous_edge);
+            }
+        }
+        if let Some(face) = face {
+            // The loop is gone if it only had these two edges.
+            let start = if previous_edge != opposite_edge { Some(next_opposite_edge) } else { None };
+            self.replace_loop_start(face, edge, start);
+            self.replace_loop_start(face, opposite_edge, start);
+        }
+
+        self.discard_edge(edge);
+        self.discard_edge(opposite_edge);
+        self.discard_vertex(vertex);
+        Ok(())
+    }
+
+    pub fn make_edge_kill_ring(&mut self, edge1: HalfEdgeId, edge2: HalfEdgeId) -> HalfEdgeId {
+        self.try_make_edge_kill_ring(edge1, edge2).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// MEKR: joins the vertices of `edge1` and `edge2`, which lie on two loops
+    /// of the same facet, merging the loops. Returns the edge going to the
+    /// vertex of `edge2`.
+    pub fn try_make_edge_kill_ring(&mut self, edge1: HalfEdgeId, edge2: HalfEdgeId) -> Result<HalfEdgeId, HalfEdgeError> {
+        self.record(|poly| poly.apply_make_edge_kill_ring(edge1, edge2))
+    }
+
+    fn apply_make_edge_kill_ring(&mut self, edge1: HalfEdgeId, edge2: HalfEdgeId) -> Result<HalfEdgeId, HalfEdgeError> {
+        let face = self.try_face(edge1)?;
+        let other_face = self.try_face(edge2)?;
+        if face != other_face {
+            return Err(HalfEdgeError::FacetMismatch(face, other_face));
+        }
+        let cycle1 = self.try_cycle(edge1)?;
+        if cycle1.contains(&edge2) {
+            return Err(HalfEdgeError::SameLoop(edge1, edge2));
+        }
+        let cycle2 = self.try_cycle(edge2)?;
+
+        let vertex1 = self.try_vertex(edge1)?;
+        let vertex2 = self.try_vertex(edge2)?;
+        let next1 = get_element!(self.edges[edge1], next);
+        let next2 = get_element!(self.edges[edge2], next);
+
+        let new_edge = self.edges.insert(HalfEdge {
+            vertex: Some(vertex2),
+            face: Some(face),
+            opposite: None,
+            next: Some(next2),
+        });
+        let new_opposite_edge = self.edges.insert(HalfEdge {
+            vertex: Some(vertex1),
+            face: Some(face),
+            opposite: Some(new_edge),
+            next: Some(next1),
+        });
+        self.edges[new_edge].opposite = Some(new_opposite_edge);
+        self.edges[edge1].next = Some(new_edge);
+        self.edges[edge2].next = Some(new_opposite_edge);
+        self.corner_properties.interpolate(new_edge, &[(edge2, 1.)]);
+        self.corner_properties.interpolate(new_opposite_edge, &[(edge1, 1.)]);
+
+        // The merged loop keeps the main place if either loop had it.
+        let (start1, start2) = (self.loop_start(face, &cycle1), self.loop_start(face, &cycle2));
+        let dropped = if self.facets[face].edge == start2 { start1 } else { start2 };
+        if let Some(dropped) = dropped {
+            self.replace_loop_start(face, dropped, None);
+        }
+
+        Ok(new_edge)
+    }
+
+    pub fn kill_edge_make_ring(&mut self, edge: HalfEdgeId) {
+        self.try_kill_edge_make_ring(edge).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// KEMR: removes `edge`, whose two sides are on the same loop of a facet,
+    /// splitting the loop into two loops of that facet.
+    pub fn try_kill_edge_make_ring(&mut self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        self.record(|poly| poly.apply_kill_edge_make_ring(edge))
+    }
+
+    fn apply_kill_edge_make_ring(&mut self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        let opposite_edge = try_element!(self.edges, edge, opposite, MissingOpposite)?;
+        let face = self.try_face(edge)?;
+        let vertex = self.try_vertex(edge)?;
+        let opposite_vertex = self.try_vertex(opposite_edge)?;
+        let cycle = self.try_cycle(edge)?;
+        let position = match cycle.iter().position(|e| *e == opposite_edge) {
+            Some(position) => position,
+            None => return Err(HalfEdgeError::NotBridge(edge)),
+        };
+        // Both new loops need an edge.
+        if position == 1 || position == cycle.len() - 1 {
+            return Err(HalfEdgeError::NotBridge(edge));
+        }
+
+        let previous_edge = cycle[cycle.len() - 1];
+        let previous_opposite_edge = cycle[position - 1];
+        let next_edge = cycle[1];
+        let next_opposite_edge = cycle[(position + 1) % cycle.len()];
+
+        self.edges[previous_edge].next = Some(next_opposite_edge);
+        self.edges[previous_opposite_edge].next = Some(next_edge);
+
+        if self.vertices[vertex].edge == Some(edge) {
+            self.vertices[vertex].edge = Some(previous_opposite_edge);
+        }
+        if self.vertices[opposite_vertex].edge == Some(opposite_edge) {
+            self.vertices[opposite_vertex].edge = Some(previous_edge);
+        }
+        // One of the two loops keeps the place of the split one, the other
+        // becomes a ring.
+        if let Some(start) = self.loop_start(face, &cycle) {
+            let kept = if start == edge || start == opposite_edge { next_edge } else { start };
+            self.replace_loop_start(face, start, Some(kept));
+            let ring = if cycle[1..position].contains(&kept) { next_opposite_edge } else { next_edge };
+            self.facets[face].rings.push(ring);
+        }
+
+        self.discard_edge(edge);
+        self.discard_edge(opposite_edge);
+        Ok(())
+    }
+
+    // Finds the edge going from `from` to `to`.
+    fn try_find_edge(&self, from: VertexId, to: VertexId) -> Result<Option<HalfEdgeId>, HalfEdgeError> {
+        let edge = match self.vertices[to].edge {
+            Some(edge) => edge,
+            None => return Ok(None),
+        };
+        for incoming in self.try_fan(edge)? {
+            let opposite = try_element!(self.edges, incoming, opposite, MissingOpposite)?;
+            if self.try_vertex(opposite)? == from {
+                return Ok(Some(incoming));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn add_facet(&mut self, vertices: &[VertexId]) -> FacetId {
+        self.try_add_facet(vertices).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Adds a facet going through `vertices` in order. The vertices must be on
+    /// the border or have no edge yet, and existing edges between them must be
+    /// border edges, so the facet extends the mesh or closes a hole.
+    pub fn try_add_facet(&mut self, vertices: &[VertexId]) -> Result<FacetId, HalfEdgeError> {
+        self.record(|poly| poly.apply_add_facet(vertices))
+    }
+
+    fn apply_add_facet(&mut self, vertices: &[VertexId]) -> Result<FacetId, HalfEdgeError> {
+        let n = vertices.len();
+        if n < 3 || (1..n).any(|i| vertices[..i].contains(&vertices[i])) {
+            return Err(HalfEdgeError::DegenerateFacet);
+        }
+
+        let mut isolated = Vec::with_capacity(n);
+        for vertex in vertices {
+            let edge = self.vertices.get(*vertex).ok_or(HalfEdgeError::RemovedElement((*vertex).into()))?.edge;
+            match edge {
+                Some(edge) => {
+                    if !self.try_fan(edge)?.iter().any(|e| self.edges[*e].is_border()) {
+                        return Err(HalfEdgeError::InteriorVertex(*vertex));
+                    }
+                    isolated.push(false);
+                }
+                None => isolated.push(true),
+            }
+        }
+
+        let mut halfedges = Vec::with_capacity(n);
+        for i in 0..n {
+            let edge = self.try_find_edge(vertices[i], vertices[(i + 1) % n])?;
+            if let Some(edge) = edge {
+                if !self.edges[edge].is_border() {
+                    return Err(HalfEdgeError::InteriorEdge(edge));
+                }
+            }
+            halfedges.push(edge);
+        }
+
+        // A vertex reached by two new edges goes into a gap of its fan. With
+        // several gaps, it takes the one bounded by the border going to or
+        // coming from its neighbours in the facet, any of them if none is, and
+        // none if several are.
+        let mut gaps = vec![None; n];
+        for i in 0..n {
+            let ii = (i + 1) % n;
+            if halfedges[i].is_some() || halfedges[ii].is_some() || isolated[ii] {
+                continue;
+            }
+            let vertex = vertices[ii];
+            let mut candidates: Vec<HalfEdgeId> = self.vertex_incoming_halfedges(vertex)
+                .filter(|e| self.edges[*e].is_border())
+                .collect();
+            if candidates.len() > 1 {
+                let mut bounded = Vec::new();
+                for edge in &candidates {
+                    if self.try_gap_bounded_by(*edge, vertices[i], vertices[(ii + 1) % n])? {
+                        bounded.push(*edge);
+                    }
+                }
+                if bounded.is_empty() {
+                    candidates.truncate(1);
+                } else {
+                    candidates = bounded;
+                }
+            }
+            if candidates.len() != 1 {
+                return Err(HalfEdgeError::NonManifoldVertex(vertex));
+            }
+            gaps[ii] = Some(candidates[0]);
+        }
+
+        // Border loops going between two consecutive existing edges are moved
+        // to another gap around their vertex. The new links are kept aside
+        // until every check has passed.
+        let mut links: HashMap<HalfEdgeId, HalfEdgeId> = HashMap::new();
+        {
+            let next = |links: &HashMap<HalfEdgeId, HalfEdgeId>, edge: HalfEdgeId| {
+                links.get(&edge).cloned().unwrap_or_else(|| get_element!(self.edges[edge], next))
+            };
+            for i in 0..n {
+                let (inner_previous, inner_next) = match (halfedges[i], halfedges[(i + 1) % n]) {
+                    (Some(previous), Some(next)) => (previous, next),
+                    _ => continue,
+                };
+                if next(&links, inner_previous) == inner_next {
+                    continue;
+                }
+
+                let vertex = vertices[(i + 1) % n];
+                let mut border_previous = get_element!(self.edges[inner_next], opposite);
+                let mut count = 0;
+                while {
+                    border_previous = get_element!(self.edges[next(&links, border_previous)], opposite);
+                    count += 1;
+                    if count > self.edges.len() {
+                        return Err(HalfEdgeError::NonManifoldVertex(vertex));
+                    }
+                    !self.edges[border_previous].is_border() || border_previous == inner_previous
+                } {}
+                let border_next = next(&links, border_previous);
+                if border_next == inner_next {
+                    return Err(HalfEdgeError::NonManifoldVertex(vertex));
+                }
+
+                let patch_start = next(&links, inner_previous);
+                let mut patch_end = inner_next;
+                while next(&links, patch_end) != inner_next {
+                    patch_end = next(&links, patch_end);
+                }
+                links.insert(border_previous, patch_start);
+                links.insert(patch_end, border_next);
+                links.insert(inner_previous, inner_next);
+            }
+        }
+        for (edge, next) in links {
+            self.edges[edge].next = Some(next);
+        }
+
+        let is_new: Vec<bool> = halfedges.iter().map(Option::is_none).collect();
+        let halfedges: Vec<HalfEdgeId> = halfedges.iter().enumerate().map(|(i, edge)| match *edge {
+            Some(edge) => edge,
+            None => {
+                let new_edge = self.edges.insert(HalfEdge {
+                    vertex: Some(vertices[(i + 1) % n]),
+                    face: None,
+                    opposite: None,
+                    next: None,
+                });
+                let new_opposite_edge = self.edges.insert(HalfEdge {
+                    vertex: Some(vertices[i]),
+                    face: None,
+                    opposite: Some(new_edge),
+                    next: None,
+                });
+                self.edges[new_edge].opposite = Some(new_opposite_edge);
+                new_edge
+            }
+        }).collect();
+
+        let facet = self.facets.insert(Facet { edge: Some(halfedges[0]), rings: Vec::new() });
+
+        // Links around each vertex are computed on the mesh before this loop, then set.
+        let mut links = Vec::new();
+        for i in 0..n {
+            let ii = (i + 1) % n;
+            let vertex = vertices[ii];
+            let (inner_previous, inner_next) = (halfedges[i], halfedges[ii]);
+            let outer_previous = get_element!(self.edges[inner_next], opposite);
+            let outer_next = get_element!(self.edges[inner_previous], opposite);
+
+            match (is_new[i], is_new[ii]) {
+                (true, false) => {
+                    let border_previous = self.get_prev_edge(inner_next);
+                    links.push((border_previous, outer_next));
+                }
+                (false, true) => {
+                    let border_next = get_element!(self.edges[inner_previous], next);
+                    links.push((outer_previous, border_next));
+                }
+                (true, true) if isolated[ii] => {
+                    links.push((outer_previous, outer_next));
+                }
+                (true, true) => {
+                    let border_previous = gaps[ii].ok_or(HalfEdgeError::NonManifoldVertex(vertex))?;
+                    let border_next = get_element!(self.edges[border_previous], next);
+                    links.push((border_previous, outer_next));
+                    links.push((outer_previous, border_next));
+                }
+                (false, false) => {}
+            }
+            links.push((inner_previous, inner_next));
+        }
+        for (edge, next) in links {
+            self.edges[edge].next = Some(next);
+        }
+
+        for i in 0..n {
+            self.edges[halfedges[i]].face = Some(facet);
+            let vertex = vertices[(i + 1) % n];
+            if self.vertices[vertex].edge.is_none() {
+                self.vertices[vertex].edge = Some(halfedges[i]);
+            }
+        }
+        Ok(facet)
+    }
+
+    pub fn remove_facet(&mut self, facet: FacetId) {
+        self.try_remove_facet(facet).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Turns `facet` into a hole. Edges left with no facet on either side are
+    /// removed, and so are vertices left with no edge.
+    pub fn try_remove_facet(&mut self, facet: FacetId) -> Result<(), HalfEdgeError> {
+        self.record(|poly| poly.apply_remove_facet(facet))
+    }
+
+    fn apply_remove_facet(&mut self, facet: FacetId) -> Result<(), HalfEdgeError> {
+        try_element!(self.facets, facet, edge, MissingEdge)?;
+        let mut facet_edges: Vec<HalfEdgeId> = Vec::new();
+        for start in self.try_facet_loops(facet)? {
+            facet_edges.extend(self.try_cycle(start)?);
+        }
+        for e in &facet_edges {
+            let opposite = try_element!(self.edges, *e, opposite, MissingOpposite)?;
+            self.try_vertex(opposite)?;
+            try_element!(self.edges, opposite, next, MissingNext)?;
+        }
+
+        for e in &facet_edges {
+            self.edges[*e].face = None;
+        }
+        self.discard_facet(facet);
+
+        let mut touched_vertices = Vec::new();
+        for e in facet_edges {
+            let opposite_edge = match self.edges.get(e) {
+                Some(edge) => get_element!(edge, opposite),
+                None => continue,
+            };
+            if !self.edges[opposite_edge].is_border() {
+                continue;
+            }
+
+            let vertex = get_element!(self.edges[e], vertex);
+            let opposite_vertex = get_element!(self.edges[opposite_edge], vertex);
+            let next_edge = get_element!(self.edges[e], next);
+            let next_opposite_edge = get_element!(self.edges[opposite_edge], next);
+            let previous_edge = self.get_prev_edge(e);
+            let previous_opposite_edge = self.get_prev_edge(opposite_edge);
+
+            if previous_edge != opposite_edge {
+                self.edges[previous_edge].next = Some(next_opposite_edge);
+            }
+            if previous_opposite_edge != e {
+                self.edges[previous_opposite_edge].next = Some(next_edge);
+            }
+
+            if self.vertices[vertex].edge == Some(e) {
+                self.vertices[vertex].edge = if previous_opposite_edge == e { None } else { Some(previous_opposite_edge) };
+            }
+            if self.vertices[opposite_vertex].edge == Some(opposite_edge) {
+                self.vertices[opposite_vertex].edge = if previous_edge == opposite_edge { None } else { Some(previous_edge) };
+            }
+            touched_vertices.push(vertex);
+            touched_vertices.push(opposite_vertex);
+
+            self.discard_edge(e);
+            self.discard_edge(opposite_edge);
+        }
+
+        for vertex in touched_vertices {
+            if self.vertices.get(vertex).is_some_and(|v| v.edge.is_none()) {
+                self.discard_vertex(vertex);
+            }
+        }
+        Ok(())
+    }
+}
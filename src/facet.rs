@@ -1,50 +1,24 @@
-
-
-use std::rc::Rc;
-
-
-use utils::Handle;
-use pos::{Pos, Pos2};
-use halfedge::{HalfEdge, HalfEdge2};
-
-pub struct Facet<T : Pos> {
-    pub edge: Option<Handle<HalfEdge<T>>>,
+use arena::Arena;
+use halfedge::{self, HalfEdge};
+use utils::HalfEdgeId;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Facet {
+    pub edge: Option<HalfEdgeId>,
+    /// An edge of each loop of the facet besides the one of `edge`.
+    pub rings: Vec<HalfEdgeId>,
 }
 
-impl<T: Pos> Facet<T> {
-    pub fn degree(&self) -> i32 {
-        if let Some(ref original_edge) = self.edge {
-            let mut current_edge = Rc::clone(&original_edge);
-            let mut count = 0;
-            while {
-                      let new_edge = match &current_edge.borrow().next {
-                          &Some(ref edge) => Rc::clone(&edge),
-                          &None => panic!("Edge has no next edge"),
-                      };
-                      current_edge = new_edge;
-
-                      count += 1;
-                      current_edge != *original_edge
-                  } {}
-            count
-
+impl Facet {
+    pub fn degree(&self, edges: &Arena<HalfEdgeId, HalfEdge>) -> i32 {
+        if let Some(original_edge) = self.edge {
+            halfedge::cycle_length(edges, original_edge).unwrap_or_else(|error| panic!("{}", error))
         } else {
             panic!("A facet has no associated edge.");
         }
     }
 
-    pub fn new() -> Facet<T> {
-        Facet { edge: None }
+    pub fn new() -> Facet {
+        Facet { edge: None, rings: Vec::new() }
     }
 }
-
-impl<T: Pos> PartialEq for Facet<T> {
-    fn eq(&self, other: &Facet<T>) -> bool {
-        self as *const _ == other as *const _
-    }
-}
-
-impl<T: Pos> Eq for Facet<T> {}
-
-pub type Facet2 = Facet<Pos2>;
-
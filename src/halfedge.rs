@@ -1,29 +1,18 @@
-use std::cmp::{Eq, PartialEq};
-use std::rc::Rc;
+use arena::Arena;
+use error::HalfEdgeError;
+use utils::{VertexId, HalfEdgeId, FacetId};
 
-use pos::{Pos, Pos2};
-use vertex::{Vertex, Vertex2};
-use facet::{Facet, Facet2};
-use utils::Handle;
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HalfEdge {
+    pub vertex: Option<VertexId>,
+    pub face: Option<FacetId>,
 
-pub struct HalfEdge<T : Pos> {
-    pub vertex: Option<Handle<Vertex<T>>>,
-    pub face: Option<Handle<Facet<T>>>,
-
-    pub opposite: Option<Handle<HalfEdge<T>>>,
-    pub next: Option<Handle<HalfEdge<T>>>,
-}
-
-impl<T : Pos> PartialEq for HalfEdge<T> {
-    fn eq(&self, other: &HalfEdge<T>) -> bool {
-        self as *const _ == other as *const _
-    }
+    pub opposite: Option<HalfEdgeId>,
+    pub next: Option<HalfEdgeId>,
 }
 
-impl<T : Pos> Eq for HalfEdge<T> {}
-
-impl<T:Pos> HalfEdge<T> {
-    pub fn new() -> HalfEdge<T> {
+impl HalfEdge {
+    pub fn new() -> HalfEdge {
         HalfEdge {
             vertex: None,
             face: None,
@@ -31,7 +20,43 @@ impl<T:Pos> HalfEdge<T> {
             next: None,
         }
     }
+
+    /// Border half-edges lie outside the mesh and have no face.
+    pub fn is_border(&self) -> bool {
+        self.face.is_none()
+    }
 }
 
+/// Number of half-edges in the `next` cycle going through `edge`.
+pub fn cycle_length(edges: &Arena<HalfEdgeId, HalfEdge>, edge: HalfEdgeId) -> Result<i32, HalfEdgeError> {
+    let mut current_edge = edge;
+    let mut count = 0;
+    while {
+        current_edge = try_element!(edges, current_edge, next, MissingNext)?;
+
+        count += 1;
+        if count as usize > edges.len() {
+            return Err(HalfEdgeError::BrokenCycle(edge));
+        }
+        current_edge != edge
+    } {}
+    Ok(count)
+}
 
-pub type HalfEdge2 = HalfEdge<Pos2>;
+/// Number of half-edges ending at the vertex of `edge`, found by turning
+/// around it with `next` and `opposite`.
+pub fn fan_length(edges: &Arena<HalfEdgeId, HalfEdge>, edge: HalfEdgeId) -> Result<i32, HalfEdgeError> {
+    let mut current_edge = edge;
+    let mut count = 0;
+    while {
+        let new_edge = try_element!(edges, current_edge, next, MissingNext)?;
+        current_edge = try_element!(edges, new_edge, opposite, MissingOpposite)?;
+
+        count += 1;
+        if count as usize > edges.len() {
+            return Err(HalfEdgeError::BrokenCycle(edge));
+        }
+        current_edge != edge
+    } {}
+    Ok(count)
+}
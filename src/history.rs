@@ -0,0 +1,243 @@
+use std::any::Any;
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use error::HalfEdgeError;
+use facet::Facet;
+use halfedge::HalfEdge;
+use polyhedron::Polyhedron;
+use pos::Pos;
+use utils::*;
+use vertex::Vertex;
+
+// Undo and redo for the mesh operators. While recording, the arenas keep the
+// previous state of every slot an operator modifies, and that set of slots is
+// pushed on the undo stack. Swapping the slots back reverts the operator with
+// the same ids, and gives the set of slots to swap again to redo it.
+// Transactions use the same journals to revert a failed batch of operators.
+
+/// Reverts a transaction whose edit panics, and gives back the journal of the
+/// enclosing transaction, so that a caught panic leaves no journal open.
+struct Unwinding<'a, T: Pos + 'a> {
+    poly: &'a mut Polyhedron<T>,
+    outer: Option<Change<T>>,
+    armed: bool,
+}
+
+impl<'a, T: Pos> Drop for Unwinding<'a, T> {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let change = self.poly.close_journals();
+        self.poly.swap_change(change);
+        if let Some(outer) = self.outer.take() {
+            self.poly.resume_journals(outer);
+        }
+    }
+}
+
+/// Previous states of the slots modified by one operator, and previous
+/// property values keyed by map and slot index.
+pub(crate) struct Change<T: Pos> {
+    pub(crate) vertices: Vec<(VertexId, Option<Vertex<T>>)>,
+    pub(crate) edges: Vec<(HalfEdgeId, Option<HalfEdge>)>,
+    pub(crate) facets: Vec<(FacetId, Option<Facet>)>,
+    pub(crate) vertex_values: Vec<((usize, usize), Box<dyn Any>)>,
+    pub(crate) corner_values: Vec<((usize, usize), Box<dyn Any>)>,
+    pub(crate) facet_values: Vec<((usize, usize), Box<dyn Any>)>,
+}
+
+pub(crate) struct History<T: Pos> {
+    recording: bool,
+    undo: Vec<Change<T>>,
+    redo: Vec<Change<T>>,
+}
+
+impl<T: Pos> History<T> {
+    pub(crate) fn new() -> History<T> {
+        History {
+            recording: false,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+impl<T: Pos> Polyhedron<T> {
+    /// Starts pushing every operator call on the undo stack.
+    pub fn start_recording(&mut self) {
+        self.history.recording = true;
+    }
+
+    /// Stops recording and forgets the undo and redo stacks.
+    pub fn stop_recording(&mut self) {
+        self.history = History::new();
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.history.recording
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.history.redo.is_empty()
+    }
+
+    /// Reverts the last recorded operator. Returns false if there is none.
+    pub fn undo(&mut self) -> bool {
+        match self.history.undo.pop() {
+            Some(change) => {
+                let inverse = self.swap_change(change);
+                self.history.redo.push(inverse);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies again the last undone operator. Returns false if there is none.
+    pub fn redo(&mut self) -> bool {
+        match self.history.redo.pop() {
+            Some(change) => {
+                let inverse = self.swap_change(change);
+                self.history.undo.push(inverse);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves a vertex, recording the move like the operators.
+    pub fn set_position(&mut self, vertex: VertexId, position: T) {
+        self.try_set_position(vertex, position).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_set_position(&mut self, vertex: VertexId, position: T) -> Result<(), HalfEdgeError> {
+        self.record(|poly| {
+            poly.vertices.get_mut(vertex).ok_or(HalfEdgeError::RemovedElement(vertex.into()))?.position = position;
+            Ok(())
+        })
+    }
+
+    /// Runs `edit` on the mesh, reverting all its changes if it returns an
+    /// error. `edit` chains the `try_` operators with `?`. A successful
+    /// transaction is a single step for `undo`.
+    pub fn transaction<R, E, F>(&mut self, edit: F) -> Result<R, E>
+        where F: FnOnce(&mut Polyhedron<T>) -> Result<R, E>
+    {
+        // An enclosing transaction gets back its journal, with the slots
+        // modified here added to it.
+        let outer = if self.edges.is_journal_open() { Some(self.close_journals()) } else { None };
+
+        self.open_journals();
+        let mut guard = Unwinding { poly: self, outer, armed: true };
+        let result = edit(guard.poly);
+        guard.armed = false;
+        let outer = guard.outer.take();
+        let poly = &mut *guard.poly;
+
+        let change = poly.close_journals();
+        let change = if result.is_err() {
+            poly.swap_change(change);
+            None
+        } else {
+            Some(change)
+        };
+
+        match (outer, change) {
+            (Some(outer), Some(change)) => poly.resume_journals(outer.merge(change)),
+            (Some(outer), None) => poly.resume_journals(outer),
+            (None, Some(change)) => {
+                if poly.history.recording && !change.is_empty() {
+                    poly.history.undo.push(change);
+                    poly.history.redo.clear();
+                }
+            }
+            (None, None) => {}
+        }
+        result
+    }
+
+    /// Runs an operator, pushing its change on the undo stack if recording.
+    /// While recording or inside a transaction, an operator failing halfway
+    /// through is reverted.
+    pub(crate) fn record<R, F>(&mut self, operator: F) -> Result<R, HalfEdgeError>
+        where F: FnOnce(&mut Polyhedron<T>) -> Result<R, HalfEdgeError>
+    {
+        if !self.history.recording && !self.edges.is_journal_open() {
+            return operator(self);
+        }
+        self.transaction(operator)
+    }
+
+    pub(crate) fn open_journals(&mut self) {
+        self.vertices.open_journal();
+        self.edges.open_journal();
+        self.facets.open_journal();
+        self.vertex_properties.open_journal();
+        self.corner_properties.open_journal();
+        self.facet_properties.open_journal();
+    }
+
+    pub(crate) fn close_journals(&mut self) -> Change<T> {
+        Change {
+            vertices: self.vertices.close_journal(),
+            edges: self.edges.close_journal(),
+            facets: self.facets.close_journal(),
+            vertex_values: self.vertex_properties.close_journal(),
+            corner_values: self.corner_properties.close_journal(),
+            facet_values: self.facet_properties.close_journal(),
+        }
+    }
+
+    pub(crate) fn resume_journals(&mut self, change: Change<T>) {
+        self.vertices.resume_journal(change.vertices);
+        self.edges.resume_journal(change.edges);
+        self.facets.resume_journal(change.facets);
+        self.vertex_properties.resume_journal(change.vertex_values);
+        self.corner_properties.resume_journal(change.corner_values);
+        self.facet_properties.resume_journal(change.facet_values);
+    }
+
+    /// Puts back the slots of `change`, returning the slots they replaced.
+    pub(crate) fn swap_change(&mut self, change: Change<T>) -> Change<T> {
+        Change {
+            vertices: change.vertices.into_iter().map(|(id, slot)| (id, self.vertices.replace_slot(id, slot))).collect(),
+            edges: change.edges.into_iter().map(|(id, slot)| (id, self.edges.replace_slot(id, slot))).collect(),
+            facets: change.facets.into_iter().map(|(id, slot)| (id, self.facets.replace_slot(id, slot))).collect(),
+            vertex_values: change.vertex_values.into_iter().map(|(key, value)| (key, self.vertex_properties.replace_value(key, value))).collect(),
+            corner_values: change.corner_values.into_iter().map(|(key, value)| (key, self.corner_properties.replace_value(key, value))).collect(),
+            facet_values: change.facet_values.into_iter().map(|(key, value)| (key, self.facet_properties.replace_value(key, value))).collect(),
+        }
+    }
+}
+
+impl<T: Pos> Change<T> {
+    fn is_empty(&self) -> bool {
+        self.vertices.is_empty() && self.edges.is_empty() && self.facets.is_empty()
+            && self.vertex_values.is_empty() && self.corner_values.is_empty() && self.facet_values.is_empty()
+    }
+
+    /// Adds the slots of a later change, keeping the earlier state of slots
+    /// found in both.
+    fn merge(self, later: Change<T>) -> Change<T> {
+        Change {
+            vertices: merge_slots(self.vertices, later.vertices),
+            edges: merge_slots(self.edges, later.edges),
+            facets: merge_slots(self.facets, later.facets),
+            vertex_values: merge_slots(self.vertex_values, later.vertex_values),
+            corner_values: merge_slots(self.corner_values, later.corner_values),
+            facet_values: merge_slots(self.facet_values, later.facet_values),
+        }
+    }
+}
+
+fn merge_slots<K: Copy + Eq + Hash, S>(mut earlier: Vec<(K, S)>, later: Vec<(K, S)>) -> Vec<(K, S)> {
+    let known: HashSet<K> = earlier.iter().map(|&(key, _)| key).collect();
+    earlier.extend(later.into_iter().filter(|&(key, _)| !known.contains(&key)));
+    earlier
+}
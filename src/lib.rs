@@ -3,22 +3,1161 @@ extern crate rand;
 
 #[macro_use]
 pub mod utils;
+pub mod arena;
+pub mod error;
 pub mod halfedge;
 pub mod vertex;
 pub mod facet;
 pub mod polyhedron;
+pub mod circulator;
+pub mod euler;
+pub mod measure;
+pub mod locate;
+pub mod delaunay;
+pub mod history;
+pub mod property;
 pub mod pos;
+pub mod predicates;
+pub mod obj;
+pub mod off;
+pub mod ply;
+pub mod stl;
+pub mod validation;
+pub mod drawing;
 
 
 
 #[cfg(test)]
 mod tests {
+    use facet::Facet;
+    use halfedge::HalfEdge;
+    use polyhedron::Polyhedron2;
+    use utils::{FacetId, HalfEdgeId, VertexId};
+
+    type Snapshot = (Vec<(VertexId, f32, f32, Option<HalfEdgeId>)>, Vec<(HalfEdgeId, HalfEdge)>, Vec<(FacetId, Facet)>);
+
+    /// Every element of the mesh, to compare it before and after an edit.
+    fn snapshot(poly: &Polyhedron2) -> Snapshot {
+        let vertices: Vec<_> = poly.vertices.iter().map(|(id, v)| (id, v.position.x, v.position.y, v.edge)).collect();
+        let edges: Vec<_> = poly.edges.iter().map(|(id, e)| (id, *e)).collect();
+        let facets: Vec<_> = poly.facets.iter().map(|(id, f)| (id, f.clone())).collect();
+        (vertices, edges, facets)
+    }
+
     #[test]
     fn test_svg() {
         use polyhedron::Polyhedron2;
         let poly = Polyhedron2::create_triangle();
-        poly.draw_svg("./poly.svg", 500u32, 500u32);
+        let path = ::std::env::temp_dir().join("poly.svg");
+        poly.draw_svg(path.to_str().unwrap(), 500u32, 500u32).unwrap();
+        let document = ::std::fs::read_to_string(&path).unwrap();
+        assert!(document.contains("viewBox=\"-10 -10 220 220\""));
+        assert_eq!(document.matches("<path").count(), 1);
+    }
+
+    #[test]
+    fn test_center_vertex() {
+        use polyhedron::Polyhedron2;
+        let mut poly = Polyhedron2::create_rectangle(0., 0., 100., 100.);
+        let edge = poly.edges.ids().next().unwrap();
+
+        let center = poly.create_center_vertex(edge);
+        assert_eq!(poly.vertices_size(), 5);
+        assert_eq!(poly.edges_size(), 16);
+        assert_eq!(poly.facets_size(), 4);
+        assert_eq!(poly.vertices[center].degree(&poly.edges), 4);
+
+        let spoke = poly.vertices[center].edge.unwrap();
+        poly.erase_center_vertex(spoke);
+        assert_eq!(poly.vertices_size(), 4);
+        assert_eq!(poly.edges_size(), 8);
+        assert_eq!(poly.facets_size(), 1);
+    }
+
+    #[test]
+    fn test_removed_elements_are_reclaimed() {
+        use polyhedron::Polyhedron2;
+        let mut poly = Polyhedron2::create_regular_polygon(0., 0., 10., 6);
+        let edge = poly.edges.ids().next().unwrap();
+
+        let center = poly.create_center_vertex(edge);
+        let capacities = (poly.vertices.capacity(), poly.edges.capacity(), poly.facets.capacity());
+
+        let mut spoke = poly.vertices[center].edge.unwrap();
+        for _ in 0..100 {
+            poly.erase_center_vertex(spoke);
+            let edge = poly.edges.ids().next().unwrap();
+            let center = poly.create_center_vertex(edge);
+            spoke = poly.vertices[center].edge.unwrap();
+        }
+
+        assert_eq!((poly.vertices.capacity(), poly.edges.capacity(), poly.facets.capacity()), capacities);
+        assert_eq!(poly.edges_size(), 24);
+    }
+
+    #[test]
+    fn test_invalid_operations_return_errors() {
+        use polyhedron::Polyhedron2;
+        use error::HalfEdgeError;
+        let mut poly = Polyhedron2::create_triangle();
+        let edge = poly.edges.ids().next().unwrap();
+        let vertex = poly.edges[edge].vertex.unwrap();
+
+        let border_edge = poly.edges[edge].opposite.unwrap();
+        assert_eq!(poly.try_flip_edge(edge), Err(HalfEdgeError::BoundaryEdge(edge)));
+        assert_eq!(poly.try_join_facet(border_edge), Err(HalfEdgeError::BoundaryEdge(border_edge)));
+        assert_eq!(poly.try_vertex_degree(vertex), Ok(2));
+
+        let center = poly.create_center_vertex(edge);
+        let spoke = poly.vertices[center].edge.unwrap();
+        let other = poly.edges[poly.edges[spoke].next.unwrap()].opposite.unwrap();
+        let (face, other_face) = (poly.edges[spoke].face.unwrap(), poly.edges[other].face.unwrap());
+        assert_eq!(poly.try_split_facet(spoke, other), Err(HalfEdgeError::FacetMismatch(face, other_face)));
+        assert_eq!(poly.try_split_facet(spoke, spoke), Err(HalfEdgeError::SameEdge(spoke)));
+        assert_eq!(poly.try_split_vertex(spoke, spoke), Err(HalfEdgeError::SameEdge(spoke)));
+        assert_eq!(poly.try_erase_center_vertex(edge), Err(HalfEdgeError::BoundaryVertex(vertex)));
+
+        assert_eq!(poly.vertices_size(), 4);
+        assert_eq!(poly.edges_size(), 12);
+        assert_eq!(poly.facets_size(), 3);
+    }
+
+    #[test]
+    fn test_validate() {
+        use polyhedron::Polyhedron2;
+        use validation::Violation;
+        let mut poly = Polyhedron2::create_regular_polygon(0., 0., 10., 5);
+        assert!(poly.validate().is_valid());
+
+        let edge = poly.edges.ids().next().unwrap();
+        let center = poly.create_center_vertex(edge);
+        let spoke = poly.vertices[center].edge.unwrap();
+        poly.flip_edge(poly.edges[spoke].next.unwrap());
+        assert!(poly.validate().is_valid());
+
+        let next = poly.edges[spoke].next.unwrap();
+        poly.join_facet(next);
+        assert!(poly.validate().is_valid());
+
+        let center = poly.create_center_vertex(edge);
+        let spoke = poly.vertices[center].edge.unwrap();
+        let other = poly.edges[poly.edges[spoke].next.unwrap()].opposite.unwrap();
+        let other = poly.edges[poly.edges[other].next.unwrap()].opposite.unwrap();
+        let new_vertex = poly.split_vertex(spoke, other);
+        assert!(poly.validate().is_valid());
+
+        assert_eq!(poly.edges[other].vertex, Some(new_vertex));
+        let joined = poly.edges[other].next.unwrap();
+        poly.join_vertex(joined);
+        assert!(poly.validate().is_valid());
+        assert!(!poly.vertices.contains(new_vertex));
+
+        let next = poly.edges[spoke].next.unwrap();
+        poly.edges[spoke].next = None;
+        let report = poly.validate();
+        assert!(report.violations.contains(&Violation::MissingNext(spoke)));
+        assert!(report.violations.contains(&Violation::OpenCycle(next)));
     }
 
+    #[test]
+    fn test_circulators() {
+        use polyhedron::Polyhedron2;
+        let mut poly = Polyhedron2::create_rectangle(0., 0., 100., 100.);
+        let edge = poly.edges.ids().next().unwrap();
+        let corner = poly.edges[edge].vertex.unwrap();
+        let center = poly.create_center_vertex(edge);
+
+        assert_eq!(poly.vertex_neighbors(center).count(), 4);
+        assert_eq!(poly.vertex_facets(center).count(), 4);
+        assert!(poly.vertex_outgoing_halfedges(center).all(|e| poly.edges[poly.get_prev_edge(e)].vertex == Some(center)));
+
+        assert!(poly.is_border_vertex(corner));
+        assert_eq!(poly.vertex_incoming_halfedges(corner).count(), 3);
+        assert_eq!(poly.vertex_outgoing_halfedges(corner).count(), 3);
+        assert_eq!(poly.vertex_facets(corner).count(), 2);
+        let mut neighbors: Vec<_> = poly.vertex_neighbors(corner).collect();
+        neighbors.sort();
+        neighbors.dedup();
+        assert_eq!(neighbors.len(), 3);
+        assert!(neighbors.contains(&center));
+        assert_eq!(poly.vertices[corner].degree(&poly.edges), 3);
+
+        for facet in poly.facets.ids() {
+            assert_eq!(poly.facet_halfedges(facet).count(), 3);
+            assert!(poly.facet_vertices(facet).any(|v| v == center));
+        }
+
+        poly.join_vertex(edge);
+        assert!(poly.validate().is_valid());
+        assert_eq!(poly.vertices_size(), 4);
+    }
+
+    #[test]
+    fn test_tetrahedron() {
+        use polyhedron::Polyhedron3;
+        let mut poly = Polyhedron3::create_tetrahedron();
+        assert!(poly.validate().is_valid());
+        assert_eq!(poly.edges_size(), 12);
+        for vertex in poly.vertices.ids() {
+            assert_eq!(poly.vertices[vertex].degree(&poly.edges), 3);
+            assert!(!poly.is_border_vertex(vertex));
+        }
+
+        let facet = poly.facets.ids().next().unwrap();
+        let edge = poly.facets[facet].edge.unwrap();
+        let center = poly.create_center_vertex(edge);
+        poly.flip_edge(edge);
+        assert!(poly.validate().is_valid());
+        assert_eq!(poly.vertices[center].degree(&poly.edges), 4);
+        assert_eq!(poly.facets_size(), 6);
+    }
+
+    #[test]
+    fn test_from_faces() {
+        use polyhedron::Polyhedron2;
+        use pos::Pos2;
+        use error::MeshBuildError;
+        let positions: Vec<_> = (0..7).map(|i| Pos2 { x: (i % 3) as f32, y: (i / 3) as f32 }).collect();
+
+        let poly = Polyhedron2::from_faces(&positions, &[vec![0, 1, 4, 3], vec![1, 2, 5, 4]]).unwrap();
+        assert!(poly.validate().is_valid());
+        assert_eq!(poly.vertices_size(), 6);
+        assert_eq!(poly.edges_size(), 14);
+        assert_eq!(poly.facets_size(), 2);
+
+        assert_eq!(Polyhedron2::from_faces(&positions, &[vec![0, 1, 4, 3], vec![1, 4, 5, 2]]).err(),
+                   Some(MeshBuildError::InconsistentOrientation(1, 4)));
+        assert_eq!(Polyhedron2::from_faces(&positions, &[vec![0, 1, 3], vec![1, 0, 4], vec![0, 1, 5]]).err(),
+                   Some(MeshBuildError::NonManifoldEdge(0, 1)));
+        assert_eq!(Polyhedron2::from_faces(&positions, &[vec![0, 1, 3], vec![1, 2, 4]]).err(),
+                   Some(MeshBuildError::NonManifoldVertex(1)));
+        // With two such vertices, the first one met in face order is reported.
+        assert_eq!(Polyhedron2::from_faces(&positions, &[vec![0, 1, 3], vec![1, 2, 4], vec![3, 5, 6]]).err(),
+                   Some(MeshBuildError::NonManifoldVertex(1)));
+        assert_eq!(Polyhedron2::from_faces(&positions, &[vec![0, 1, 3], vec![3, 5, 6], vec![1, 2, 4]]).err(),
+                   Some(MeshBuildError::NonManifoldVertex(3)));
+        assert_eq!(Polyhedron2::from_faces(&positions, &[vec![0, 1, 9]]).err(),
+                   Some(MeshBuildError::IndexOutOfBounds { face: 0, index: 9 }));
+        assert_eq!(Polyhedron2::from_faces(&positions, &[vec![0, 1, 0]]).err(),
+                   Some(MeshBuildError::DegenerateFace(0)));
+    }
+
+    #[test]
+    fn test_obj() {
+        use std::io::Cursor;
+        use polyhedron::{Polyhedron2, Polyhedron3};
+        let poly = Polyhedron3::create_tetrahedron();
+        let mut buffer = Vec::new();
+        poly.write_obj(&mut buffer).unwrap();
+
+        let loaded = Polyhedron3::read_obj(Cursor::new(&buffer)).unwrap();
+        assert!(loaded.validate().is_valid());
+        assert_eq!(loaded.vertices_size(), 4);
+        assert_eq!(loaded.facets_size(), 4);
+
+        let square = "# square\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1\nf -4 -2 -1\n";
+        let poly = Polyhedron2::read_obj(Cursor::new(square)).unwrap();
+        assert_eq!(poly.facets_size(), 2);
+        assert_eq!(poly.edges_size(), 10);
+
+        let mut buffer = Vec::new();
+        poly.write_obj(&mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.starts_with("v 0 0 0\nv 1 0 0\n"));
+        assert_eq!(text.lines().filter(|line| line.starts_with("f ")).count(), 2);
+    }
+
+    #[test]
+    fn test_svg_options() {
+        use drawing::SvgOptions;
+        use utils::ElementId;
+        use polyhedron::Polyhedron2;
+        use pos::Pos2;
+        let poly = Polyhedron2::create_rectangle(10., 20., 30., 40.);
+        let options = SvgOptions {
+            flip_y: true,
+            margin: 0.,
+            fill: Box::new(|f| format!("#{:02x}0000", f.index())),
+            vertex_radius: Some(1.),
+            ..SvgOptions::default()
+        };
+        let document = poly.to_svg_document(&options).to_string();
+        assert!(document.contains("viewBox=\"10 20 40 30\""));
+        assert!(document.contains("fill=\"#000000\""));
+        assert_eq!(document.matches("<circle").count(), 4);
+
+        assert!(poly.draw_svg_with("/nonexistent/poly.svg", &options).is_err());
+
+        // A ring is drawn as a second subpath of its facet, a hole for the
+        // even-odd rule.
+        let mut poly = Polyhedron2::create_rectangle(0., 0., 10., 10.);
+        let corner = poly.edges.ids().find(|e| !poly.edges[*e].is_border()).unwrap();
+        let w1 = poly.make_edge_vertex(corner, Pos2 { x: 2., y: 2. });
+        let bridge = poly.vertices[w1].edge.unwrap();
+        let w2 = poly.make_edge_vertex(bridge, Pos2 { x: 4., y: 2. });
+        let w3 = poly.make_edge_vertex(poly.vertices[w2].edge.unwrap(), Pos2 { x: 2., y: 4. });
+        poly.split_facet(poly.vertices[w3].edge.unwrap(), bridge);
+        poly.kill_edge_make_ring(bridge);
+        let document = poly.to_svg_document(&SvgOptions::default()).to_string();
+        assert_eq!(document.matches("<path").count(), 2);
+        assert_eq!(document.matches("fill-rule=\"evenodd\"").count(), 2);
+        assert_eq!(document.matches('M').count(), 3);
+    }
+
+    #[test]
+    fn test_debug_svg() {
+        use drawing::SvgOptions;
+        use polyhedron::Polyhedron2;
+        let mut poly = Polyhedron2::create_rectangle(0., 0., 100., 100.);
+        let edge = poly.edges.ids().find(|edge| !poly.edges[*edge].is_border()).unwrap();
+        poly.create_center_vertex(edge);
+
+        let document = poly.to_debug_svg_document(&SvgOptions::debug()).to_string();
+        for id in 0..poly.edges_size() {
+            assert!(document.contains(&format!("\ne{}\n", id)));
+        }
+        assert!(document.contains("\nv4\n"));
+        assert!(document.contains("\nf3\n"));
+        assert_eq!(document.matches("stroke-dasharray").count(), 4);
+        let path = ::std::env::temp_dir().join("poly_debug.svg");
+        poly.draw_debug_svg(path.to_str().unwrap(), &SvgOptions::debug()).unwrap();
+    }
+
+    #[test]
+    fn test_off() {
+        use std::io::Cursor;
+        use polyhedron::{Polyhedron2, Polyhedron3};
+        let poly = Polyhedron3::create_tetrahedron();
+        let mut buffer = Vec::new();
+        poly.write_off(&mut buffer).unwrap();
+        assert!(buffer.starts_with(b"OFF\n4 4 6\n"));
+
+        let loaded = Polyhedron3::read_off(Cursor::new(&buffer)).unwrap();
+        assert!(loaded.validate().is_valid());
+        assert_eq!(loaded.facets_size(), 4);
+
+        let square = "COFF # colours are skipped\n4 1 4\n0 0 0 255 0 0 255\n1 0 0 0 255 0 255\n\n1 1 0 0 0 255 255\n0 1 0 9 9 9 255\n4 0 1 2 3 1 1 1\n";
+        let poly = Polyhedron2::read_off(Cursor::new(square)).unwrap();
+        assert_eq!(poly.facets_size(), 1);
+        assert_eq!(poly.edges_size(), 8);
+        assert!(Polyhedron2::read_off(Cursor::new("OFF\n4 1 0\n0 0\n")).is_err());
+    }
+
+    #[test]
+    fn test_ply() {
+        use std::io::Cursor;
+        use polyhedron::Polyhedron3;
+        use ply::{PlyFormat, VertexAttributes};
+        let poly = Polyhedron3::create_tetrahedron();
+        let attributes = VertexAttributes {
+            colors: Some(vec![[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255], [10, 20, 30, 40]]),
+            normals: Some(vec![[0., 0., 1.], [0., 1., 0.], [1., 0., 0.], [0.5, 0.5, 0.5]]),
+        };
+        for format in &[PlyFormat::Ascii, PlyFormat::BinaryLittleEndian, PlyFormat::BinaryBigEndian] {
+            let mut buffer = Vec::new();
+            poly.write_ply_with_attributes(&mut buffer, *format, &attributes).unwrap();
+            let (loaded, loaded_attributes) = Polyhedron3::read_ply_with_attributes(Cursor::new(&buffer)).unwrap();
+            assert!(loaded.validate().is_valid());
+            assert_eq!(loaded.facets_size(), 4);
+            assert_eq!(loaded_attributes, attributes);
+            for (id, vertex) in loaded.vertices.iter() {
+                assert!(poly.vertices[id].position == vertex.position);
+            }
+        }
+
+        let quads = "ply\nformat ascii 1.0\ncomment two quads\nelement vertex 6\nproperty double x\nproperty double y\nproperty double z\n\
+                     property float red\nproperty float green\nproperty float blue\nelement face 2\nproperty list uchar uint vertex_index\n\
+                     element edge 1\nproperty int vertex1\nproperty int vertex2\nend_header\n\
+                     0 0 0 1 0 0\n1 0 0 1 0 0\n1 1 0 1 0 0\n0 1 0 1 0 0\n2 0 0 0 0.5 0\n2 1 0 0 0 1\n4 0 1 2 3\n4 1 4 5 2\n0 1\n";
+        let (poly, attributes) = Polyhedron3::read_ply_with_attributes(Cursor::new(quads)).unwrap();
+        assert_eq!(poly.facets_size(), 2);
+        assert!(poly.facets.ids().all(|f| poly.facet_vertices(f).count() == 4));
+        assert_eq!(attributes.normals, None);
+        assert_eq!(attributes.colors.unwrap()[4], [0, 128, 0, 255]);
+
+        assert!(Polyhedron3::read_ply(Cursor::new("ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nend_header\n0\n")).is_err());
+    }
+
+    #[test]
+    fn test_stl() {
+        use std::io::Cursor;
+        use polyhedron::Polyhedron3;
+        use pos::Pos3;
+        use stl::StlFormat;
+        let poly = Polyhedron3::create_tetrahedron();
+        for format in &[StlFormat::Ascii, StlFormat::Binary] {
+            let mut buffer = Vec::new();
+            poly.write_stl(&mut buffer, *format).unwrap();
+            let loaded = Polyhedron3::read_stl(Cursor::new(&buffer), 0.).unwrap();
+            assert!(loaded.validate().is_valid());
+            assert_eq!(loaded.vertices_size(), 4);
+            assert_eq!(loaded.edges_size(), 12);
+        }
+
+        // Quads are written as two triangles.
+        let positions = [Pos3 { x: 0., y: 0., z: 0. }, Pos3 { x: 1., y: 0., z: 0. }, Pos3 { x: 1., y: 1., z: 0. }, Pos3 { x: 0., y: 1., z: 0. }];
+        let quad = Polyhedron3::from_faces(&positions, &[vec![0, 1, 2, 3]]).unwrap();
+        let mut buffer = Vec::new();
+        quad.write_stl(&mut buffer, StlFormat::Binary).unwrap();
+        assert_eq!(buffer.len(), 84 + 2 * 50);
+
+        // A binary file whose header starts with "solid" and that has trailing bytes.
+        let mut padded = b"solid padded".to_vec();
+        padded.extend_from_slice(&buffer[12..]);
+        padded.extend_from_slice(b"\n\n");
+        let loaded = Polyhedron3::read_stl(Cursor::new(&padded), 0.).unwrap();
+        assert_eq!((loaded.vertices_size(), loaded.facets_size()), (4, 2));
+        assert!(Polyhedron3::read_stl(Cursor::new(&padded[..100]), 0.).is_err());
+        let empty = Polyhedron3::read_stl(Cursor::new("solid empty\nendsolid empty\n"), 0.).unwrap();
+        assert_eq!(empty.vertices_size(), 0);
+
+        // A non-convex facet is split into triangles that do not overlap.
+        let p = |x: f32, y: f32| Pos3 { x, y, z: 0. };
+        let arrow = [p(0., 0.), p(2., 1.), p(4., 0.), p(2., 3.)];
+        let concave = Polyhedron3::from_faces(&arrow, &[vec![0, 1, 2, 3]]).unwrap();
+        let mut buffer = Vec::new();
+        concave.write_stl(&mut buffer, StlFormat::Ascii).unwrap();
+        let loaded = Polyhedron3::read_stl(Cursor::new(&buffer), 0.).unwrap();
+        let area: f32 = loaded.facets.ids().map(|facet| loaded.get_area(loaded.facets[facet].edge.unwrap())).sum();
+        assert!((area - 4.).abs() < 1e-4, "{}", area);
+
+        // Corners a little apart are welded, giving a diagonal that can be flipped.
+        let square = "solid square\n\
+                      facet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 1 1 0\nendloop\nendfacet\n\
+                      facet normal 0 0 1\nouter loop\nvertex 0.0001 0 0\nvertex 1 1.0001 0\nvertex 0 1 0\nendloop\nendfacet\n\
+                      endsolid square\n";
+        assert_eq!(Polyhedron3::read_stl(Cursor::new(square), 0.).unwrap().vertices_size(), 6);
+        let mut poly = Polyhedron3::read_stl(Cursor::new(square), 1e-3).unwrap();
+        assert_eq!(poly.vertices_size(), 4);
+        assert_eq!(poly.edges_size(), 10);
+        let diagonal = poly.edges.ids().find(|edge| !poly.is_border_edge(*edge)).unwrap();
+        poly.flip_edge(diagonal);
+        assert!(poly.validate().is_valid());
+        poly.join_vertex(diagonal);
+        assert!(poly.validate().is_valid());
+        assert_eq!(poly.vertices_size(), 3);
+
+        // A triangle welded onto another one is dropped, whatever its orientation.
+        let doubled = "solid doubled\n\
+                       facet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 1 1 0\nendloop\nendfacet\n\
+                       facet normal 0 0 1\nouter loop\nvertex 1 0 0\nvertex 1 1.0001 0\nvertex 0.0001 0 0\nendloop\nendfacet\n\
+                       facet normal 0 0 -1\nouter loop\nvertex 0 0 0.0001\nvertex 1 1 0\nvertex 1 0 0\nendloop\nendfacet\n\
+                       facet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 1 0\nvertex 0 1 0\nendloop\nendfacet\n\
+                       endsolid doubled\n";
+        let poly = Polyhedron3::read_stl(Cursor::new(doubled), 1e-3).unwrap();
+        assert!(poly.validate().is_valid());
+        assert_eq!((poly.vertices_size(), poly.edges_size(), poly.facets_size()), (4, 10, 2));
+    }
+
+    #[test]
+    fn test_collapse_edge() {
+        use error::HalfEdgeError;
+        use polyhedron::{Polyhedron2, Polyhedron3};
+        use pos::Pos2;
+        use utils::ElementId;
+        let mut positions: Vec<Pos2> = (0..6)
+            .map(|i| Pos2 { x: (i as f32 * 1.047).cos(), y: (i as f32 * 1.047).sin() })
+            .collect();
+        positions.push(Pos2 { x: 0., y: 0. });
+        let faces: Vec<Vec<usize>> = (0..6).map(|i| vec![6, i, (i + 1) % 6]).collect();
+        let mut poly = Polyhedron2::from_faces(&positions, &faces).unwrap();
+
+        // Both ends on the border, but not a border edge.
+        let square = Polyhedron2::from_faces(&positions[..4], &[vec![0, 1, 2], vec![0, 2, 3]]).unwrap();
+        let diagonal = square.edges.ids().find(|e| !square.is_border_edge(*e)).unwrap();
+        assert!(!square.is_collapsible(diagonal));
+
+        let spoke = poly.edges.ids()
+            .find(|e| poly.edges[*e].vertex.unwrap().index() == 6 && !poly.is_border_edge(*e))
+            .unwrap();
+        let border = poly.edges.ids().find(|e| poly.edges[*e].is_border()).unwrap();
+        assert!(poly.is_collapsible(spoke));
+        let vertex = poly.collapse_edge(spoke, Pos2 { x: 0.5, y: 0. });
+        assert!(poly.validate().is_valid());
+        assert_eq!(poly.vertices_size(), 6);
+        assert_eq!(poly.facets_size(), 4);
+        assert!(poly.vertices[vertex].position == Pos2 { x: 0.5, y: 0. });
+
+        let vertex = poly.collapse_edge(border, Pos2 { x: 0., y: 1. });
+        assert!(poly.validate().is_valid());
+        assert_eq!(poly.vertices_size(), 5);
+        assert_eq!(poly.facets_size(), 3);
+        assert!(poly.is_border_vertex(vertex));
+
+        let mut triangle = Polyhedron2::create_triangle();
+        let edge = triangle.edges.ids().next().unwrap();
+        assert_eq!(triangle.try_collapse_edge(edge, Pos2 { x: 0., y: 0. }), Err(HalfEdgeError::NotCollapsible(edge)));
+        assert_eq!(triangle.vertices_size(), 3);
+        assert!(triangle.validate().is_valid());
 
+        let mut poly = Polyhedron3::create_tetrahedron();
+        let edge = poly.edges.ids().next().unwrap();
+        let position = poly.vertices[poly.edges[edge].vertex.unwrap()].position;
+        poly.collapse_edge(edge, position);
+        assert!(poly.validate().is_valid());
+        assert_eq!((poly.vertices_size(), poly.edges_size(), poly.facets_size()), (3, 6, 2));
+        assert!(poly.edges.ids().all(|e| !poly.is_collapsible(e)));
+    }
+
+    #[test]
+    fn test_split_edge() {
+        use error::HalfEdgeError;
+        use polyhedron::Polyhedron2;
+        use pos::Pos2;
+        let mut poly = Polyhedron2::create_rectangle(0., 0., 100., 100.);
+        let edge = poly.edges.ids().find(|e| !poly.edges[*e].is_border()).unwrap();
+        let face = poly.edges[edge].face.unwrap();
+        assert_eq!(poly.try_split_edge_and_triangulate(edge, 0.5), Err(HalfEdgeError::NotTriangle(face)));
+        for t in &[-0.5, 1.5, f32::NAN, f32::INFINITY] {
+            assert_eq!(poly.try_split_edge(edge, *t), Err(HalfEdgeError::InvalidSplit(edge)));
+        }
+        assert_eq!(poly.vertices_size(), 4);
+
+        let vertex = poly.split_edge(edge, 0.25);
+        assert!(poly.validate().is_valid());
+        assert_eq!(poly.edges[edge].vertex, Some(vertex));
+        assert_eq!(poly.facets[face].degree(&poly.edges), 5);
+        assert_eq!(poly.vertices[vertex].degree(&poly.edges), 2);
+        assert!(poly.vertices[vertex].position == Pos2 { x: 75., y: 0. });
+
+        let positions = [Pos2 { x: 0., y: 0. }, Pos2 { x: 1., y: 0. }, Pos2 { x: 1., y: 1. }, Pos2 { x: 0., y: 1. }];
+        let mut poly = Polyhedron2::from_faces(&positions, &[vec![0, 1, 2], vec![0, 2, 3]]).unwrap();
+        let diagonal = poly.edges.ids().find(|e| !poly.is_border_edge(*e)).unwrap();
+        let border = poly.edges.ids().find(|e| poly.edges[*e].is_border()).unwrap();
+
+        let center = poly.split_edge_and_triangulate(diagonal, 0.5);
+        assert!(poly.validate().is_valid());
+        assert!(poly.vertices[center].position == Pos2 { x: 0.5, y: 0.5 });
+        assert_eq!(poly.vertices[center].degree(&poly.edges), 4);
+        assert_eq!(poly.facets_size(), 4);
+        assert!(poly.facets.ids().all(|f| poly.facets[f].degree(&poly.edges) == 3));
+
+        let vertex = poly.split_edge_and_triangulate(border, 0.5);
+        assert!(poly.validate().is_valid());
+        assert_eq!(poly.facets_size(), 5);
+        assert_eq!(poly.vertices[vertex].degree(&poly.edges), 3);
+        assert!(poly.is_border_vertex(vertex));
+    }
+
+    #[test]
+    fn test_is_flippable() {
+        use error::HalfEdgeError;
+        use polyhedron::{Polyhedron2, Polyhedron3};
+        use pos::Pos2;
+        use utils::{ElementId, VertexId};
+        let mut tetrahedron = Polyhedron3::create_tetrahedron();
+        let edge = tetrahedron.edges.ids().next().unwrap();
+        assert!(!tetrahedron.is_flippable(edge));
+        assert_eq!(tetrahedron.try_flip_edge(edge), Err(HalfEdgeError::NotFlippable(edge)));
+        assert!(tetrahedron.validate().is_valid());
+
+        let dart = [Pos2 { x: 0., y: 0. }, Pos2 { x: 2., y: 1. }, Pos2 { x: 0., y: 2. }, Pos2 { x: 1., y: 1. }];
+        let mut poly = Polyhedron2::from_faces(&dart, &[vec![0, 1, 3], vec![1, 2, 3]]).unwrap();
+        let diagonal = poly.edges.ids().find(|e| !poly.is_border_edge(*e)).unwrap();
+        let border = poly.edges.ids().find(|e| poly.edges[*e].is_border()).unwrap();
+        assert!(!poly.is_flippable(border));
+        assert!(poly.is_flippable(diagonal));
+        assert!(!poly.is_flippable_convex(diagonal));
+        let before = snapshot(&poly);
+        assert_eq!(poly.try_flip_edge(diagonal), Err(HalfEdgeError::NotFlippable(diagonal)));
+        assert!(snapshot(&poly) == before);
+
+        poly.vertices[VertexId::new(3)].position = Pos2 { x: -1., y: 1. };
+        assert!(poly.is_flippable_convex(diagonal));
+        poly.flip_edge(diagonal);
+        assert!(poly.validate().is_valid());
+        assert!(poly.is_flippable_convex(diagonal));
+    }
+
+    #[test]
+    fn test_euler_operators() {
+        use error::HalfEdgeError;
+        use polyhedron::Polyhedron2;
+        use pos::Pos2;
+        use vertex::Vertex2;
+        let check = |poly: &Polyhedron2| {
+            let report = poly.validate();
+            assert!(report.is_valid(), "{}", report);
+            let counts = poly.euler_counts();
+            assert_eq!(counts.genus(), Some(0), "{:?}", counts);
+        };
+        let p = |x: f32, y: f32| Pos2 { x, y };
+
+        // A shell grown from a single vertex, then taken apart.
+        let mut poly = Polyhedron2::new();
+        let (v0, f0) = poly.make_vertex_facet_shell(p(0., 0.));
+        check(&poly);
+        let v1 = poly.make_first_edge_vertex(v0, f0, p(1., 0.));
+        check(&poly);
+        let e01 = poly.vertices[v1].edge.unwrap();
+        assert_eq!(poly.try_kill_vertex_facet_shell(v0, f0), Err(HalfEdgeError::NotIsolated(v0.into())));
+        let v2 = poly.make_edge_vertex(e01, p(0., 1.));
+        check(&poly);
+        let e12 = poly.vertices[v2].edge.unwrap();
+        assert_eq!(poly.try_join_facet(e12), Err(HalfEdgeError::SameFacet(f0)));
+        check(&poly);
+        poly.kill_edge_vertex(e12);
+        check(&poly);
+        let v2 = poly.make_edge_vertex(e01, p(0., 1.));
+        let e12 = poly.vertices[v2].edge.unwrap();
+        let f1 = poly.split_facet(e12, poly.edges[e01].opposite.unwrap());
+        check(&poly);
+        assert_eq!(poly.euler_counts().characteristic(), 2);
+
+        poly.remove_facet(f1);
+        check(&poly);
+        assert_eq!(poly.euler_counts().border_loops, 1);
+        let border = poly.edges.ids().find(|e| poly.edges[*e].is_border()).unwrap();
+        let hole: Vec<_> = poly.loop_vertices(border).collect();
+        poly.add_facet(&hole);
+        check(&poly);
+        assert_eq!(poly.euler_counts().border_loops, 0);
+
+        let facets: Vec<_> = poly.facets.ids().collect();
+        poly.remove_facet(facets[0]);
+        poly.remove_facet(facets[1]);
+        check(&poly);
+        assert_eq!((poly.vertices_size(), poly.edges_size(), poly.facets_size()), (0, 0, 0));
+
+        let (v0, f0) = poly.make_vertex_facet_shell(p(0., 0.));
+        let v1 = poly.make_first_edge_vertex(v0, f0, p(1., 0.));
+        poly.kill_edge_vertex(poly.vertices[v1].edge.unwrap());
+        check(&poly);
+        poly.kill_vertex_facet_shell(v0, f0);
+        assert_eq!((poly.vertices_size(), poly.edges_size(), poly.facets_size()), (0, 0, 0));
+
+        // A triangle hanging inside a square, turned into a ring of the square.
+        let mut poly = Polyhedron2::create_rectangle(0., 0., 10., 10.);
+        let corner = poly.edges.ids().find(|e| !poly.edges[*e].is_border()).unwrap();
+        let w1 = poly.make_edge_vertex(corner, p(2., 2.));
+        let bridge = poly.vertices[w1].edge.unwrap();
+        let w2 = poly.make_edge_vertex(bridge, p(4., 2.));
+        let w3 = poly.make_edge_vertex(poly.vertices[w2].edge.unwrap(), p(2., 4.));
+        poly.split_facet(poly.vertices[w3].edge.unwrap(), bridge);
+        check(&poly);
+        let next = poly.edges[corner].next.unwrap();
+        assert_eq!(poly.try_make_edge_kill_ring(corner, next), Err(HalfEdgeError::SameLoop(corner, next)));
+        assert_eq!(poly.try_kill_edge_make_ring(corner), Err(HalfEdgeError::NotBridge(corner)));
+
+        poly.kill_edge_make_ring(bridge);
+        check(&poly);
+        assert_eq!(poly.euler_counts().rings, 1);
+        assert_eq!(poly.euler_counts().shells, 1);
+        let face = poly.edges[corner].face;
+        let ring = poly.edges.ids()
+            .find(|e| poly.edges[*e].face == face && !poly.loop_halfedges(corner).any(|other| other == *e))
+            .unwrap();
+        assert_eq!(poly.facets[face.unwrap()].rings.len(), 1);
+        let (mut holed, _) = poly.deep_clone();
+        let holed_face = holed.facets.ids().find(|f| !holed.facets[*f].rings.is_empty()).unwrap();
+        holed.remove_facet(holed_face);
+        check(&holed);
+        // Only the hanging triangle is left.
+        assert_eq!((holed.vertices_size(), holed.facets_size(), holed.euler_counts().border_loops), (3, 1, 1));
+        assert_eq!(poly.try_split_facet(corner, ring), Err(HalfEdgeError::DifferentLoops(corner, ring)));
+        check(&poly);
+        poly.make_edge_kill_ring(corner, ring);
+        check(&poly);
+        assert_eq!(poly.euler_counts().rings, 0);
+
+        // Triangles added around a vertex in any order, gaps closing last.
+        let mut poly = Polyhedron2::new();
+        let center = poly.vertices.insert(Vertex2 { position: p(0., 0.), edge: None });
+        let ring: Vec<_> = (0..6)
+            .map(|i| poly.vertices.insert(Vertex2 { position: p((i as f32).cos(), (i as f32).sin()), edge: None }))
+            .collect();
+        for i in &[0, 2, 4, 1, 5, 3] {
+            poly.add_facet(&[center, ring[*i], ring[(*i + 1) % 6]]);
+            // Vertices with neither edge nor facet do not fit the formula.
+            if poly.vertices.iter().all(|(_, vertex)| vertex.edge.is_some()) {
+                check(&poly);
+            } else {
+                assert!(poly.validate().is_valid());
+            }
+        }
+        assert_eq!(poly.euler_counts(), ::euler::EulerCounts { vertices: 7, edges: 12, facets: 6, rings: 0, shells: 1, border_loops: 1 });
+        assert_eq!(poly.try_add_facet(&[center, ring[0], ring[1]]), Err(HalfEdgeError::InteriorVertex(center)));
+        assert_eq!(poly.try_add_facet(&[ring[0], ring[1], ring[0]]), Err(HalfEdgeError::DegenerateFacet));
+        let outside = poly.vertices.insert(Vertex2 { position: p(2., 0.), edge: None });
+        let side = poly.edges.ids()
+            .find(|e| poly.edges[*e].vertex == Some(ring[1]) && poly.edges[poly.edges[*e].opposite.unwrap()].vertex == Some(ring[0]))
+            .unwrap();
+        assert_eq!(poly.try_add_facet(&[ring[0], ring[1], outside]), Err(HalfEdgeError::InteriorEdge(side)));
+        poly.add_facet(&[ring[1], ring[0], outside]);
+        check(&poly);
+
+        let facets: Vec<_> = poly.facets.ids().collect();
+        for facet in facets {
+            poly.remove_facet(facet);
+            check(&poly);
+        }
+        assert_eq!(poly.vertices_size(), 0);
+
+        // A vertex between two pentagons has two gaps along the same border
+        // loop. A facet coming from a vertex of the first pentagon goes in the
+        // gap after it, and one also going to a vertex of it could go in either.
+        let mut poly = Polyhedron2::new();
+        let center = poly.vertices.insert(Vertex2 { position: p(0., 0.), edge: None });
+        let ring: Vec<_> = (0..12)
+            .map(|i| poly.vertices.insert(Vertex2 { position: p((i as f32).cos(), (i as f32).sin()), edge: None }))
+            .collect();
+        poly.add_facet(&[center, ring[0], ring[1], ring[2], ring[3]]);
+        poly.add_facet(&[center, ring[6], ring[7], ring[8], ring[9]]);
+        assert_eq!(poly.euler_counts().border_loops, 1);
+        let before = snapshot(&poly);
+        assert_eq!(poly.try_add_facet(&[ring[1], center, ring[2]]), Err(HalfEdgeError::NonManifoldVertex(center)));
+        assert!(snapshot(&poly) == before);
+        poly.add_facet(&[ring[1], center, ring[4]]);
+        assert!(poly.validate().is_valid());
+        assert_eq!(poly.euler_counts().border_loops, 2);
+        let border = poly.vertex_incoming_halfedges(center)
+            .find(|e| poly.edges[poly.edges[*e].opposite.unwrap()].vertex == Some(ring[0]))
+            .unwrap();
+        assert_eq!(poly.edges[poly.edges[border].next.unwrap()].vertex, Some(ring[1]));
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        use polyhedron::Polyhedron2;
+        use pos::Pos2;
+        let mut poly = Polyhedron2::create_regular_polygon(0., 0., 10., 5);
+        poly.start_recording();
+        assert!(!poly.undo());
+        let mut states = vec![snapshot(&poly)];
+
+        let edge = poly.edges.ids().find(|e| !poly.edges[*e].is_border()).unwrap();
+        let center = poly.create_center_vertex(edge);
+        states.push(snapshot(&poly));
+        let spoke = poly.vertices[center].edge.unwrap();
+        poly.flip_edge(poly.edges[spoke].next.unwrap());
+        states.push(snapshot(&poly));
+        poly.join_facet(poly.edges[spoke].next.unwrap());
+        states.push(snapshot(&poly));
+        let other = poly.edges[poly.edges[spoke].next.unwrap()].opposite.unwrap();
+        let other = poly.edges[poly.edges[other].next.unwrap()].opposite.unwrap();
+        poly.split_vertex(spoke, other);
+        states.push(snapshot(&poly));
+        poly.join_vertex(poly.edges[other].next.unwrap());
+        states.push(snapshot(&poly));
+        poly.set_position(center, Pos2 { x: 1., y: 2. });
+        states.push(snapshot(&poly));
+        poly.erase_center_vertex(poly.vertices[center].edge.unwrap());
+        states.push(snapshot(&poly));
+
+        // Failed operators leave nothing to undo.
+        assert!(poly.try_flip_edge(edge).is_err());
+
+        for state in states.iter().rev().skip(1) {
+            assert!(poly.undo());
+            assert!(poly.validate().is_valid());
+            assert!(snapshot(&poly) == *state);
+        }
+        assert!(!poly.can_undo());
+        for state in states.iter().skip(1) {
+            assert!(poly.redo());
+            assert!(snapshot(&poly) == *state);
+        }
+        assert!(!poly.redo());
+
+        // A new operator drops the undone ones.
+        poly.undo();
+        poly.undo();
+        poly.create_center_vertex(edge);
+        assert!(!poly.can_redo());
+        assert!(poly.validate().is_valid());
+    }
+
+    #[test]
+    fn test_transaction() {
+        use error::HalfEdgeError;
+        use polyhedron::Polyhedron2;
+        let mut poly = Polyhedron2::create_regular_polygon(0., 0., 10., 6);
+        let edge = poly.edges.ids().find(|e| !poly.edges[*e].is_border()).unwrap();
+        let border = poly.edges[edge].opposite.unwrap();
+        let before = snapshot(&poly);
+
+        let result = poly.transaction(|tx| {
+            let center = tx.try_create_center_vertex(edge)?;
+            let spoke = tx.vertices[center].edge.unwrap();
+            tx.try_flip_edge(tx.edges[spoke].next.unwrap())?;
+            tx.try_flip_edge(border)
+        });
+        assert_eq!(result, Err(HalfEdgeError::BoundaryEdge(border)));
+        assert!(poly.validate().is_valid());
+        assert!(snapshot(&poly) == before);
+
+        // Errors of any type roll back, and a failed inner transaction only
+        // reverts its own changes.
+        let result: Result<(), String> = poly.transaction(|tx| {
+            tx.create_center_vertex(edge);
+            let inner: Result<(), HalfEdgeError> = tx.transaction(|tx| {
+                tx.try_join_facet(tx.edges[edge].next.unwrap())?;
+                tx.try_join_facet(border)
+            });
+            assert!(inner.is_err());
+            assert_eq!(tx.facets_size(), 6);
+            Err("cancelled".to_string())
+        });
+        assert!(result.is_err());
+        assert!(snapshot(&poly) == before);
+
+        poly.start_recording();
+        poly.transaction(|tx| {
+            let center = tx.try_create_center_vertex(edge)?;
+            tx.try_join_facet(tx.vertices[center].edge.unwrap())
+        }).unwrap();
+        assert!(poly.validate().is_valid());
+        assert_eq!(poly.facets_size(), 5);
+        assert!(poly.undo());
+        assert!(!poly.can_undo());
+        assert!(snapshot(&poly) == before);
+
+        // A panic caught outside a transaction reverts it and closes its journals.
+        let caught = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+            poly.transaction(|tx| -> Result<(), ()> {
+                tx.transaction(|inner| -> Result<(), ()> {
+                    inner.create_center_vertex(edge);
+                    panic!("interrupted");
+                })
+            })
+        }));
+        assert!(caught.is_err());
+        assert!(!poly.edges.is_journal_open() && !poly.vertices.is_journal_open() && !poly.facets.is_journal_open());
+        assert!(snapshot(&poly) == before);
+        assert!(!poly.can_undo());
+    }
+
+    #[test]
+    fn test_deep_clone() {
+        use polyhedron::Polyhedron2;
+        let mut poly = Polyhedron2::create_regular_polygon(0., 0., 10., 5);
+        let edge = poly.edges.ids().find(|e| !poly.edges[*e].is_border()).unwrap();
+        let center = poly.create_center_vertex(edge);
+        let spoke = poly.vertices[center].edge.unwrap();
+        poly.join_facet(poly.edges[spoke].next.unwrap());
+
+        let (mut copy, map) = poly.deep_clone();
+        assert!(copy.validate().is_valid());
+        assert_eq!((copy.vertices_size(), copy.edges_size(), copy.facets_size()), (poly.vertices_size(), poly.edges_size(), poly.facets_size()));
+        // The removed slots are left out.
+        assert_eq!(copy.edges.capacity(), copy.edges_size());
+        for (id, e) in poly.edges.iter() {
+            let new_edge = copy.edges[map.edges[&id]];
+            assert_eq!(new_edge.next, e.next.map(|next| map.edges[&next]));
+            assert_eq!(new_edge.opposite, e.opposite.map(|opposite| map.edges[&opposite]));
+            assert_eq!(new_edge.vertex, e.vertex.map(|vertex| map.vertices[&vertex]));
+            assert_eq!(new_edge.face, e.face.map(|facet| map.facets[&facet]));
+        }
+        assert!(copy.vertices[map.vertices[&center]].position == poly.vertices[center].position);
+
+        // Edits on the copy leave the original alone.
+        copy.erase_center_vertex(map.edges[&spoke]);
+        assert!(copy.validate().is_valid());
+        assert_eq!(copy.facets_size(), 1);
+        assert_eq!(poly.facets_size(), 4);
+        assert!(poly.validate().is_valid());
+    }
+
+    #[test]
+    fn test_properties() {
+        use polyhedron::Polyhedron2;
+        use property::PropertyHandle;
+        use utils::{VertexId, HalfEdgeId, FacetId};
+        // Temperatures are the x coordinates and UVs the positions of the
+        // vertices, so blending them must agree with the positions.
+        let check = |poly: &Polyhedron2, temperature: PropertyHandle<VertexId, f32>, uv: PropertyHandle<HalfEdgeId, [f32; 2]>, color: PropertyHandle<FacetId, [u8; 4]>| {
+            assert!(poly.validate().is_valid());
+            for (id, vertex) in poly.vertices.iter() {
+                assert!((poly.property(temperature, id) - vertex.position.x).abs() < 1e-3, "{:?}", id);
+            }
+            for (id, edge) in poly.edges.iter().filter(|&(_, edge)| !edge.is_border()) {
+                let position = poly.vertices[edge.vertex.unwrap()].position;
+                let value = poly.property(uv, id);
+                assert!((value[0] - position.x).abs() < 1e-3 && (value[1] - position.y).abs() < 1e-3, "{:?}", id);
+            }
+            for id in poly.facets.ids() {
+                assert_eq!(*poly.property(color, id), [255, 0, 0, 255]);
+            }
+        };
+
+        let mut poly = Polyhedron2::create_rectangle(0., 0., 100., 100.);
+        let temperature = poly.add_vertex_property::<f32>("temperature");
+        let uv = poly.add_corner_property::<[f32; 2]>("uv");
+        let color = poly.add_facet_property::<[u8; 4]>("color");
+        assert!(poly.add_vertex_property::<f32>("temperature") == temperature);
+        assert!(poly.find_property::<VertexId, f32>("temperature") == Some(temperature));
+        assert!(poly.find_property::<VertexId, i32>("temperature").is_none());
+
+        let vertices: Vec<_> = poly.vertices.iter().map(|(id, vertex)| (id, vertex.position)).collect();
+        for (id, position) in vertices {
+            poly.set_property(temperature, id, position.x);
+        }
+        let corners: Vec<_> = poly.edges.iter().map(|(id, edge)| (id, poly.vertices[edge.vertex.unwrap()].position)).collect();
+        for (id, position) in corners {
+            *poly.property_mut(uv, id) = [position.x, position.y];
+        }
+        let facets: Vec<_> = poly.facets.ids().collect();
+        for id in facets {
+            poly.set_property(color, id, [255, 0, 0, 255]);
+        }
+        check(&poly, temperature, uv, color);
+
+        let edge = poly.edges.ids().find(|e| !poly.edges[*e].is_border()).unwrap();
+        let middle = poly.split_edge(edge, 0.25);
+        check(&poly, temperature, uv, color);
+        let center = poly.create_center_vertex(edge);
+        check(&poly, temperature, uv, color);
+        let spoke = poly.vertices[center].edge.unwrap();
+        poly.flip_edge(poly.edges[spoke].next.unwrap());
+        check(&poly, temperature, uv, color);
+        poly.join_facet(poly.edges[spoke].next.unwrap());
+        check(&poly, temperature, uv, color);
+        let other = poly.edges[poly.edges[spoke].next.unwrap()].opposite.unwrap();
+        poly.split_vertex(spoke, other);
+        check(&poly, temperature, uv, color);
+        // Removed elements get back the default value.
+        poly.erase_center_vertex(poly.vertices[center].edge.unwrap());
+        check(&poly, temperature, uv, color);
+        assert_eq!(*poly.property(temperature, center), 0.);
+
+        // Copies keep their values, and undo restores them.
+        let (mut copy, map) = poly.deep_clone();
+        check(&copy, temperature, uv, color);
+        let vertex = poly.vertices.ids().next().unwrap();
+        copy.start_recording();
+        copy.set_property(temperature, map.vertices[&vertex], 7.);
+        assert_eq!(*poly.property(temperature, vertex), poly.vertices[vertex].position.x);
+        copy.undo();
+        check(&copy, temperature, uv, color);
+        let edge = copy.edges.ids().find(|e| !copy.edges[*e].is_border()).unwrap();
+        copy.create_center_vertex(edge);
+        copy.undo();
+        copy.redo();
+        check(&copy, temperature, uv, color);
+
+        // A collapsed edge blends its vertices and corners by where the new
+        // position lies along it.
+        let edge = copy.edges.ids().find(|e| copy.is_collapsible(*e)).unwrap();
+        let origin = copy.vertices[copy.edges[copy.edges[edge].opposite.unwrap()].vertex.unwrap()].position;
+        let target = copy.vertices[copy.edges[edge].vertex.unwrap()].position;
+        let position = origin + (target - origin) * 0.25;
+        let vertex = copy.collapse_edge(edge, position);
+        assert!((copy.property(temperature, vertex) - position.x).abs() < 1e-3);
+        check(&copy, temperature, uv, color);
+
+        // Joined vertices blend their values, and so do the corners of the
+        // facets along the joined edge.
+        let joined = poly.vertices[middle].edge.unwrap();
+        let other = poly.edges[poly.edges[joined].opposite.unwrap()].vertex.unwrap();
+        let previous = poly.get_prev_edge(joined);
+        let expected = (poly.property(temperature, other) + poly.property(temperature, middle)) / 2.;
+        poly.join_vertex(joined);
+        assert!(poly.validate().is_valid());
+        assert_eq!(*poly.property(temperature, middle), expected);
+        assert_eq!(poly.property(uv, previous)[0], poly.vertices[middle].position.x);
+
+        poly.remove_property(temperature);
+        assert!(poly.find_property::<VertexId, f32>("temperature").is_none());
+    }
+
+    #[test]
+    fn test_predicates() {
+        use polyhedron::Polyhedron2;
+        use pos::{Pos2, Pos3};
+        use predicates::{orient2d, orient3d, incircle, Sign};
+        let p = |x: f32, y: f32| Pos2 { x, y };
+        let sign = |value: i128| if value > 0 { Sign::Positive } else if value < 0 { Sign::Negative } else { Sign::Zero };
+
+        // Points a few ulps around the line through (12, 12) and (24, 24),
+        // where f32 arithmetic gets the sign wrong. The reference works on
+        // integers scaled by 2^23.
+        let ulp = (2f32).powi(-23);
+        for i in 0..32 {
+            for j in 0..32 {
+                let a = p(0.5 + i as f32 * ulp, 0.5 + j as f32 * ulp);
+                let (ax, ay) = ((1i128 << 22) + i, (1i128 << 22) + j);
+                let (b, c) = (12i128 << 23, 24i128 << 23);
+                let expected = sign((b - ax) * (c - ay) - (b - ay) * (c - ax));
+                assert_eq!(orient2d(a, p(12., 12.), p(24., 24.)), expected, "{} {}", i, j);
+                assert_eq!(orient2d(p(12., 12.), a, p(24., 24.)), expected.reversed());
+            }
+        }
+        assert_eq!(orient2d(p(0., 0.), p(1., 0.), p(0., 1.)), Sign::Positive);
+
+        let q = |x: f32, y: f32, z: f32| Pos3 { x, y, z };
+        let (a, b, c) = (q(0.1, 0.2, 0.3), q(1.7, 0.3, 0.9), q(0.4, 2.1, 1.3));
+        let sides = [orient3d(a, b, c, q(0., 0., -10.)), orient3d(a, b, c, q(0., 0., 10.))];
+        assert_eq!(sides, [Sign::Positive, Sign::Negative]);
+        let (x, y, z) = (q(1., 0., 0.), q(0., 1., 0.), q(0., 0., 1.));
+        assert_eq!(orient3d(x, y, z, q(0.25, 0.25, 0.5)), Sign::Zero);
+        assert_eq!(orient3d(x, y, z, q(0.25, 0.25, 0.5 + ulp)), Sign::Negative);
+        assert_eq!(orient3d(x, y, z, q(0.25, 0.25, 0.5 - ulp)), Sign::Positive);
+
+        let corners = [p(1., 0.), p(0., 1.), p(-1., 0.), p(0., -1.)];
+        assert_eq!(incircle(corners[0], corners[1], corners[2], corners[3]), Sign::Zero);
+        assert_eq!(incircle(corners[0], corners[1], corners[2], p(0., -1. + ulp)), Sign::Positive);
+        assert_eq!(incircle(corners[0], corners[1], corners[2], p(0., -1. - ulp)), Sign::Negative);
+        assert_eq!(incircle(corners[0], corners[1], corners[2], p(0., 0.)), Sign::Positive);
+
+        // A flat quadrilateral is not convex, so its diagonal cannot be flipped.
+        let positions = [p(0., 0.), p(1., 0.), p(2., 0.), p(1., 1.)];
+        let poly = Polyhedron2::from_faces(&positions, &[vec![0, 1, 3], vec![1, 2, 3]]).unwrap();
+        let diagonal = poly.edges.ids()
+            .find(|e| poly.edges[*e].opposite.is_some_and(|o| !poly.edges[o].is_border()) && !poly.edges[*e].is_border())
+            .unwrap();
+        assert!(poly.is_flippable(diagonal));
+        assert!(!poly.is_flippable_convex(diagonal));
+        let degenerate = Polyhedron2::from_faces(&[p(0., 0.), p(1., 0.), p(2., 0.)], &[vec![0, 1, 2]]).unwrap();
+        let edge = degenerate.edges.ids().next().unwrap();
+        assert_eq!(degenerate.get_area(edge), 0.);
+    }
+
+    #[test]
+    fn test_measures() {
+        use polyhedron::Polyhedron2;
+        use pos::Pos2;
+        let p = |x: f32, y: f32| Pos2 { x, y };
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-4;
+
+        // An L shape, whose vertex average is not its centroid.
+        let positions = [p(0., 0.), p(2., 0.), p(2., 1.), p(1., 1.), p(1., 2.), p(0., 2.)];
+        let mut poly = Polyhedron2::from_faces(&positions, &[vec![0, 1, 2, 3, 4, 5]]).unwrap();
+        let facet = poly.facets.ids().next().unwrap();
+        assert!(close(poly.signed_area(facet), 3.));
+        assert!(poly.is_ccw(facet));
+        assert!(close(poly.perimeter(facet), 8.));
+        let centroid = poly.centroid(facet);
+        assert!(close(centroid.x, 5. / 6.) && close(centroid.y, 5. / 6.));
+        assert!(close(poly.total_area(), 3.) && close(poly.total_signed_area(), 3.));
+        assert!(close(poly.border_length(), 8.));
+        let border = poly.edges.ids().find(|e| poly.edges[*e].is_border()).unwrap();
+        assert!(close(poly.get_area(border), 3.));
+
+        // The same polygon given clockwise.
+        let reversed = Polyhedron2::from_faces(&positions, &[vec![5, 4, 3, 2, 1, 0]]).unwrap();
+        let reversed_facet = reversed.facets.ids().next().unwrap();
+        assert!(close(reversed.signed_area(reversed_facet), -3.));
+        assert!(!reversed.is_ccw(reversed_facet));
+        assert!(close(reversed.total_area(), 3.) && close(reversed.total_signed_area(), -3.));
+
+        let edge = poly.facets[facet].edge.unwrap();
+        let center = poly.create_center_vertex_at(edge, centroid);
+        assert!(poly.vertices[center].position == centroid);
+        assert!(poly.validate().is_valid());
+        assert!(close(poly.total_signed_area(), 3.));
+        assert!(poly.facets.ids().all(|facet| poly.is_ccw(facet)));
+    }
+
+    #[test]
+    fn test_locate() {
+        use locate::Location;
+        use polyhedron::Polyhedron2;
+        use pos::Pos2;
+        let p = |x: f32, y: f32| Pos2 { x, y };
+
+        // A 4 x 4 grid of squares cut along their diagonals.
+        let n = 5;
+        let positions: Vec<_> = (0..n * n).map(|i| p((i % n) as f32, (i / n) as f32)).collect();
+        let mut faces = Vec::new();
+        for j in 0..n - 1 {
+            for i in 0..n - 1 {
+                let a = j * n + i;
+                faces.push(vec![a, a + 1, a + n + 1]);
+                faces.push(vec![a, a + n + 1, a + n]);
+            }
+        }
+        let poly = Polyhedron2::from_faces(&positions, &faces).unwrap();
+        let same_edge = |e: ::utils::HalfEdgeId, other: ::utils::HalfEdgeId| e == other || poly.edges[e].opposite == Some(other);
+
+        let facets: Vec<_> = poly.facets.ids().collect();
+        for hint in facets.iter().map(|facet| Some(*facet)).chain(Some(None)) {
+            match poly.locate_from(p(2.7, 1.2), hint) {
+                Location::InFacet(facet) => {
+                    let corners: Vec<_> = poly.facet_vertices(facet).map(|v| poly.vertices[v].position).collect();
+                    assert!(corners.contains(&p(2., 1.)) && corners.contains(&p(3., 1.)) && corners.contains(&p(3., 2.)));
+                }
+                other => panic!("{:?}", other),
+            }
+            match poly.locate_from(p(1.5, 2.5), hint) {
+                Location::OnEdge(edge) => {
+                    let diagonal = poly.edges.ids()
+                        .find(|e| poly.vertices[poly.edges[*e].vertex.unwrap()].position == p(2., 3.)
+                            && poly.vertices[poly.edges[poly.edges[*e].opposite.unwrap()].vertex.unwrap()].position == p(1., 2.))
+                        .unwrap();
+                    assert!(same_edge(edge, diagonal));
+                    assert!(!poly.edges[edge].is_border());
+                }
+                other => panic!("{:?}", other),
+            }
+            assert_eq!(poly.locate_from(p(3., 3.), hint), Location::OnVertex(::utils::ElementId::new(3 * n + 3)));
+            assert_eq!(poly.locate_from(p(4., 0.), hint), Location::OnVertex(::utils::ElementId::new(4)));
+            match poly.locate_from(p(0.25, 0.), hint) {
+                Location::OnEdge(edge) => assert!(poly.edges[poly.edges[edge].opposite.unwrap()].is_border()),
+                other => panic!("{:?}", other),
+            }
+            assert_eq!(poly.locate_from(p(4.5, 2.), hint), Location::Outside);
+            assert_eq!(poly.locate_from(p(-1., -1.), hint), Location::Outside);
+        }
+
+        // Concave facets are found by the fallback.
+        let positions = [p(0., 0.), p(2., 0.), p(2., 1.), p(1., 1.), p(1., 2.), p(0., 2.)];
+        let l_shape = Polyhedron2::from_faces(&positions, &[vec![0, 1, 2, 3, 4, 5]]).unwrap();
+        let facet = l_shape.facets.ids().next().unwrap();
+        assert_eq!(l_shape.locate(p(0.5, 1.5)), Location::InFacet(facet));
+        assert_eq!(l_shape.locate(p(1.5, 1.5)), Location::Outside);
+        assert_eq!(Polyhedron2::new().locate(p(0., 0.)), Location::Outside);
+    }
+
+    #[test]
+    fn test_make_delaunay() {
+        use polyhedron::Polyhedron2;
+        use pos::Pos2;
+        let p = |x: f32, y: f32| Pos2 { x, y };
+
+        // The long diagonal of a thin rhombus is flipped into the short one.
+        let positions = [p(0., 0.), p(4., 0.), p(2., 1.), p(2., -1.)];
+        let mut poly = Polyhedron2::from_faces(&positions, &[vec![0, 1, 2], vec![1, 0, 3]]).unwrap();
+        assert_eq!(poly.make_delaunay(), 1);
+        let diagonal = poly.edges.ids().find(|e| !poly.edges[*e].is_border()).unwrap();
+        let mut ends = vec![
+            poly.vertices[poly.edges[diagonal].vertex.unwrap()].position.y,
+            poly.vertices[poly.edges[poly.edges[diagonal].opposite.unwrap()].vertex.unwrap()].position.y,
+        ];
+        ends.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(ends, vec![-1., 1.]);
+        assert_eq!(poly.make_delaunay(), 0);
+
+        // A jittered grid cut along the same diagonals.
+        let n = 6;
+        let mut seed = 12345u32;
+        let mut jitter = || {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            ((seed >> 16) % 1000) as f32 / 1000. - 0.5
+        };
+        let positions: Vec<_> = (0..n * n).map(|i| p((i % n) as f32 * 2. + jitter(), (i / n) as f32 + jitter() * 0.8)).collect();
+        let mut faces = Vec::new();
+        for j in 0..n - 1 {
+            for i in 0..n - 1 {
+                let a = j * n + i;
+                faces.push(vec![a, a + 1, a + n + 1]);
+                faces.push(vec![a, a + n + 1, a + n]);
+            }
+        }
+        let mut poly = Polyhedron2::from_faces(&positions, &faces).unwrap();
+        let area = poly.total_signed_area();
+        let edges: Vec<_> = poly.edges.ids().collect();
+        assert!(edges.iter().any(|e| !poly.is_locally_delaunay(*e)));
+
+        poly.start_recording();
+        assert!(poly.make_delaunay() > 0);
+        assert!(poly.edges.ids().all(|e| poly.is_locally_delaunay(e)));
+        assert!(poly.facets.ids().all(|f| poly.is_ccw(f)));
+        assert!((poly.total_signed_area() - area).abs() < 1e-3);
+        assert_eq!(poly.facets_size(), 2 * (n - 1) * (n - 1));
+        assert_eq!(poly.make_delaunay(), 0);
+
+        // All the flips are undone at once.
+        assert!(poly.undo());
+        assert!(edges.iter().any(|e| !poly.is_locally_delaunay(*e)));
+    }
 }
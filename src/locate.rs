@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+
+use polyhedron::Polyhedron2;
+use pos::Pos2;
+use predicates::{orient2d, Sign};
+use utils::*;
+
+// Point location. The walk moves from facet to facet across the edges that
+// have the point on their outer side, which finds the facet in a mesh of
+// convex facets. When the walk leaves the mesh, cycles, or ends in a facet
+// that does not hold the point, every facet is tested instead.
+
+/// Where a point lies in a mesh.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Location {
+    InFacet(FacetId),
+    /// On the half-edge, in the facet of which the point was found.
+    OnEdge(HalfEdgeId),
+    OnVertex(VertexId),
+    Outside,
+}
+
+impl Polyhedron2 {
+    /// Finds the facet, edge or vertex at `point`, walking from any facet.
+    pub fn locate(&self, point: Pos2) -> Location {
+        self.locate_from(point, None)
+    }
+
+    /// Finds the facet, edge or vertex at `point`, walking from `hint`, which
+    /// should be a facet close to the point.
+    pub fn locate_from(&self, point: Pos2, hint: Option<FacetId>) -> Location {
+        let start = hint
+            .filter(|facet| self.facets.contains(*facet))
+            .or_else(|| self.facets.ids().next());
+        let mut facet = match start {
+            Some(facet) => facet,
+            None => return Location::Outside,
+        };
+
+        let mut visited = HashSet::new();
+        while visited.insert(facet) {
+            let exit = self.facet_halfedges(facet)
+                .find(|edge| self.edge_side(*edge, point) == Sign::Negative);
+            match exit {
+                None => {
+                    if let Some(location) = self.locate_in_facet(facet, point) {
+                        return location;
+                    }
+                    break;
+                }
+                Some(edge) => match self.edges[get_element!(self.edges[edge], opposite)].face {
+                    Some(next_facet) => facet = next_facet,
+                    None => break,
+                },
+            }
+        }
+        self.locate_brute_force(point)
+    }
+
+    /// Tests every facet.
+    fn locate_brute_force(&self, point: Pos2) -> Location {
+        self.facets.ids()
+            .filter_map(|facet| self.locate_in_facet(facet, point))
+            .next()
+            .unwrap_or(Location::Outside)
+    }
+
+    /// Where `point` lies in `facet`, or `None` if it is outside of it. Uses
+    /// the winding number, so that the facet may be concave.
+    fn locate_in_facet(&self, facet: FacetId, point: Pos2) -> Option<Location> {
+        let mut winding = 0;
+        for edge in self.facet_halfedges(facet) {
+            let (source, target) = self.edge_ends(edge);
+            let (a, b) = (self.vertices[source].position, self.vertices[target].position);
+            let side = orient2d(a, b, point);
+
+            if side == Sign::Zero && between(a.x, b.x, point.x) && between(a.y, b.y, point.y) {
+                return Some(if point == a {
+                    Location::OnVertex(source)
+                } else if point == b {
+                    Location::OnVertex(target)
+                } else {
+                    Location::OnEdge(edge)
+                });
+            }
+            if a.y <= point.y {
+                if b.y > point.y && side == Sign::Positive {
+                    winding += 1;
+                }
+            } else if b.y <= point.y && side == Sign::Negative {
+                winding -= 1;
+            }
+        }
+        if winding != 0 { Some(Location::InFacet(facet)) } else { None }
+    }
+
+    /// Side of the line of `edge` on which `point` lies, positive on the
+    /// side of a counterclockwise facet.
+    fn edge_side(&self, edge: HalfEdgeId, point: Pos2) -> Sign {
+        let (source, target) = self.edge_ends(edge);
+        orient2d(self.vertices[source].position, self.vertices[target].position, point)
+    }
+
+    fn edge_ends(&self, edge: HalfEdgeId) -> (VertexId, VertexId) {
+        let opposite_edge = get_element!(self.edges[edge], opposite);
+        (get_element!(self.edges[opposite_edge], vertex), get_element!(self.edges[edge], vertex))
+    }
+}
+
+fn between(a: f32, b: f32, value: f32) -> bool {
+    (a <= value && value <= b) || (b <= value && value <= a)
+}
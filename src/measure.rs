@@ -0,0 +1,123 @@
+use error::HalfEdgeError;
+use polyhedron::{Polyhedron, Polyhedron2};
+use pos::{Pos, Pos2};
+use predicates::{orient2d, Sign};
+use utils::*;
+
+// Measures of facets and meshes. Sums are accumulated in f64, relative to the
+// first vertex of each facet, to limit cancellation.
+
+fn length<T: Pos>(vector: T) -> f64 {
+    (0..T::dimension()).map(|k| f64::from(vector[k]) * f64::from(vector[k])).sum::<f64>().sqrt()
+}
+
+impl<T: Pos> Polyhedron<T> {
+    fn try_facet_positions(&self, facet: FacetId) -> Result<Vec<T>, HalfEdgeError> {
+        let edge = try_element!(self.facets, facet, edge, MissingEdge)?;
+        self.try_cycle(edge)?;
+        Ok(self.loop_vertices(edge).map(|v| self.vertices[v].position).collect())
+    }
+
+    pub fn perimeter(&self, facet: FacetId) -> f32 {
+        self.try_perimeter(facet).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_perimeter(&self, facet: FacetId) -> Result<f32, HalfEdgeError> {
+        let positions = self.try_facet_positions(facet)?;
+        let perimeter: f64 = (0..positions.len())
+            .map(|i| length(positions[(i + 1) % positions.len()] - positions[i]))
+            .sum();
+        Ok(perimeter as f32)
+    }
+
+    /// Total length of the border edges.
+    pub fn border_length(&self) -> f32 {
+        let length: f64 = self.edges.iter()
+            .filter(|&(_, edge)| edge.is_border())
+            .map(|(_, edge)| {
+                let source = get_element!(self.edges[get_element!(edge, opposite)], vertex);
+                length(self.vertices[get_element!(edge, vertex)].position - self.vertices[source].position)
+            })
+            .sum();
+        length as f32
+    }
+}
+
+impl Polyhedron2 {
+    /// Area of the facet by the shoelace formula, positive when its vertices
+    /// turn counterclockwise.
+    pub fn signed_area(&self, facet: FacetId) -> f32 {
+        self.try_signed_area(facet).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_signed_area(&self, facet: FacetId) -> Result<f32, HalfEdgeError> {
+        let positions = self.try_facet_positions(facet)?;
+        Ok(shoelace(&positions).0 as f32)
+    }
+
+    /// Whether the facet turns counterclockwise, decided exactly at its lowest
+    /// vertex. The facet is expected to be a simple polygon.
+    pub fn is_ccw(&self, facet: FacetId) -> bool {
+        self.try_is_ccw(facet).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_is_ccw(&self, facet: FacetId) -> Result<bool, HalfEdgeError> {
+        let positions = self.try_facet_positions(facet)?;
+        let n = positions.len();
+        let lowest = (0..n)
+            .min_by(|&i, &j| {
+                let (p, q) = (positions[i], positions[j]);
+                (p.y, p.x).partial_cmp(&(q.y, q.x)).unwrap_or(::std::cmp::Ordering::Equal)
+            })
+            .unwrap_or(0);
+        let sign = orient2d(positions[(lowest + n - 1) % n], positions[lowest], positions[(lowest + 1) % n]);
+        // A flat corner at the lowest vertex only happens when the facet is flat.
+        Ok(sign == Sign::Positive)
+    }
+
+    /// Centroid of the polygon of the facet. Flat facets fall back to the
+    /// average of their vertices.
+    pub fn centroid(&self, facet: FacetId) -> Pos2 {
+        self.try_centroid(facet).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_centroid(&self, facet: FacetId) -> Result<Pos2, HalfEdgeError> {
+        let positions = self.try_facet_positions(facet)?;
+        let (area, x, y) = shoelace(&positions);
+        if area == 0. {
+            let sum = positions.iter().fold(Pos2::default(), |sum, p| sum + *p);
+            return Ok(sum / positions.len() as f32);
+        }
+        let origin = positions[0];
+        Ok(Pos2 { x: origin.x + (x / (6. * area)) as f32, y: origin.y + (y / (6. * area)) as f32 })
+    }
+
+    /// Sum of the signed areas of the facets.
+    pub fn total_signed_area(&self) -> f32 {
+        let area: f64 = self.facets.ids().map(|facet| f64::from(self.signed_area(facet))).sum();
+        area as f32
+    }
+
+    /// Sum of the areas of the facets, whatever their orientation.
+    pub fn total_area(&self) -> f32 {
+        let area: f64 = self.facets.ids().map(|facet| f64::from(self.signed_area(facet).abs())).sum();
+        area as f32
+    }
+}
+
+/// Signed area of a polygon, and the sums of the centroid formula, taken
+/// relative to its first vertex.
+fn shoelace(positions: &[Pos2]) -> (f64, f64, f64) {
+    let origin = positions[0];
+    let relative = |p: Pos2| (f64::from(p.x) - f64::from(origin.x), f64::from(p.y) - f64::from(origin.y));
+    let (mut twice_area, mut x, mut y) = (0f64, 0f64, 0f64);
+    for i in 0..positions.len() {
+        let (x0, y0) = relative(positions[i]);
+        let (x1, y1) = relative(positions[(i + 1) % positions.len()]);
+        let cross = x0 * y1 - x1 * y0;
+        twice_area += cross;
+        x += (x0 + x1) * cross;
+        y += (y0 + y1) * cross;
+    }
+    (twice_area / 2., x, y)
+}
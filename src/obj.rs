@@ -0,0 +1,90 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+use polyhedron::Polyhedron;
+use pos::Pos;
+use utils::ElementId;
+
+// Wavefront OBJ files: only `v` and `f` lines are used, other lines are skipped.
+
+pub(crate) fn invalid_data<E: ToString>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+// Parses a face corner such as `3`, `3/1`, `3//2` or `-1` into a 0-based index.
+fn parse_index(corner: &str, vertex_count: usize) -> io::Result<usize> {
+    let index = corner.split('/').next().unwrap_or("");
+    let index: i64 = index.parse().map_err(|_| invalid_data(format!("invalid face index '{}'", corner)))?;
+    let resolved = if index < 0 { vertex_count as i64 + index } else { index - 1 };
+    if resolved < 0 {
+        return Err(invalid_data(format!("invalid face index '{}'", corner)));
+    }
+    Ok(resolved as usize)
+}
+
+impl<T: Pos> Polyhedron<T> {
+    /// Writes the mesh as Wavefront OBJ. Missing coordinates are written as 0.
+    pub fn write_obj<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut indices = vec![0usize; self.vertices.capacity()];
+        for (i, (id, vertex)) in self.vertices.iter().enumerate() {
+            indices[id.index()] = i + 1;
+            let position = vertex.position;
+            write!(writer, "v")?;
+            for k in 0..3 {
+                let coordinate = if k < T::dimension() { position[k] } else { 0. };
+                write!(writer, " {}", coordinate)?;
+            }
+            writeln!(writer)?;
+        }
+
+        for facet in self.facets.ids() {
+            write!(writer, "f")?;
+            for vertex in self.facet_vertices(facet) {
+                write!(writer, " {}", indices[vertex.index()])?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn save_as_obj(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_obj(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Reads a Wavefront OBJ mesh. Coordinates past the dimension of `T` are ignored.
+    pub fn read_obj<R: BufRead>(reader: R) -> io::Result<Polyhedron<T>> {
+        let mut positions = Vec::new();
+        let mut faces = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => {
+                    let coordinates = tokens
+                        .map(|token| token.parse::<f32>().map_err(invalid_data))
+                        .collect::<io::Result<Vec<f32>>>()?;
+                    if coordinates.len() < T::dimension() {
+                        return Err(invalid_data(format!("vertex with too few coordinates: '{}'", line)));
+                    }
+                    positions.push(T::from_coordinates(&coordinates));
+                }
+                Some("f") => {
+                    let face = tokens
+                        .map(|corner| parse_index(corner, positions.len()))
+                        .collect::<io::Result<Vec<usize>>>()?;
+                    faces.push(face);
+                }
+                _ => {}
+            }
+        }
+
+        Polyhedron::from_faces(&positions, &faces).map_err(invalid_data)
+    }
+
+    pub fn load_obj(path: &str) -> io::Result<Polyhedron<T>> {
+        Polyhedron::read_obj(BufReader::new(File::open(path)?))
+    }
+}
@@ -0,0 +1,113 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+use obj::invalid_data;
+use polyhedron::Polyhedron;
+use pos::Pos;
+use utils::ElementId;
+
+// Geomview OFF files. Extra values after the coordinates of a vertex or the
+// indices of a face, such as colours in COFF files, are skipped.
+
+fn parse_values<F: ::std::str::FromStr>(line: &str) -> io::Result<Vec<F>> {
+    line.split_whitespace()
+        .map(|token| token.parse::<F>().map_err(|_| invalid_data(format!("invalid value '{}'", token))))
+        .collect()
+}
+
+impl<T: Pos> Polyhedron<T> {
+    /// Writes the mesh as OFF. Missing coordinates are written as 0.
+    pub fn write_off<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "OFF")?;
+        writeln!(writer, "{} {} {}", self.vertices_size(), self.facets_size(), self.edges_size() / 2)?;
+
+        let mut indices = vec![0usize; self.vertices.capacity()];
+        for (i, (id, vertex)) in self.vertices.iter().enumerate() {
+            indices[id.index()] = i;
+            let position = vertex.position;
+            for k in 0..3 {
+                let coordinate = if k < T::dimension() { position[k] } else { 0. };
+                if k > 0 {
+                    write!(writer, " ")?;
+                }
+                write!(writer, "{}", coordinate)?;
+            }
+            writeln!(writer)?;
+        }
+
+        for facet in self.facets.ids() {
+            write!(writer, "{}", self.facet_vertices(facet).count())?;
+            for vertex in self.facet_vertices(facet) {
+                write!(writer, " {}", indices[vertex.index()])?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn save_as_off(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_off(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Reads an OFF mesh. Coordinates past the dimension of `T` are ignored.
+    pub fn read_off<R: BufRead>(reader: R) -> io::Result<Polyhedron<T>> {
+        let mut lines = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let content = line.split('#').next().unwrap_or("").trim().to_string();
+            if !content.is_empty() {
+                lines.push(content);
+            }
+        }
+        let mut lines = lines.into_iter();
+
+        // The header keyword is optional and may be followed by the counts.
+        let mut counts_line = lines.next().ok_or_else(|| invalid_data("empty OFF file"))?;
+        if let Some(keyword) = counts_line.split_whitespace().next().map(str::to_string) {
+            if keyword.ends_with("OFF") {
+                counts_line = counts_line[keyword.len()..].trim().to_string();
+                if counts_line.is_empty() {
+                    counts_line = lines.next().ok_or_else(|| invalid_data("missing OFF element counts"))?;
+                }
+            }
+        }
+        let counts: Vec<usize> = parse_values(&counts_line)?;
+        if counts.len() < 2 {
+            return Err(invalid_data(format!("invalid OFF element counts '{}'", counts_line)));
+        }
+
+        let mut positions = Vec::with_capacity(counts[0]);
+        for _ in 0..counts[0] {
+            let line = lines.next().ok_or_else(|| invalid_data("missing OFF vertices"))?;
+            let coordinates: Vec<f32> = parse_values(&line)?;
+            if coordinates.len() < T::dimension() {
+                return Err(invalid_data(format!("vertex with too few coordinates: '{}'", line)));
+            }
+            positions.push(T::from_coordinates(&coordinates));
+        }
+
+        let mut faces = Vec::with_capacity(counts[1]);
+        for _ in 0..counts[1] {
+            let line = lines.next().ok_or_else(|| invalid_data("missing OFF faces"))?;
+            let mut tokens = line.split_whitespace();
+            let size: usize = tokens.next()
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| invalid_data(format!("invalid face '{}'", line)))?;
+            let face = tokens.take(size)
+                .map(|token| token.parse::<usize>().map_err(|_| invalid_data(format!("invalid face index '{}'", token))))
+                .collect::<io::Result<Vec<usize>>>()?;
+            if face.len() < size {
+                return Err(invalid_data(format!("face with too few indices: '{}'", line)));
+            }
+            faces.push(face);
+        }
+
+        Polyhedron::from_faces(&positions, &faces).map_err(invalid_data)
+    }
+
+    pub fn load_off(path: &str) -> io::Result<Polyhedron<T>> {
+        Polyhedron::read_off(BufReader::new(File::open(path)?))
+    }
+}
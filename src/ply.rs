@@ -0,0 +1,418 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+
+use obj::invalid_data;
+use polyhedron::Polyhedron;
+use pos::Pos;
+use utils::ElementId;
+
+// Stanford PLY files. Only the `vertex` and `face` elements are used, other
+// elements and properties are read and skipped.
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// Optional per-vertex properties of a PLY file, indexed by `VertexId::index()`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VertexAttributes {
+    /// `red`, `green`, `blue` and `alpha` properties. Alpha is 255 when missing.
+    pub colors: Option<Vec<[u8; 4]>>,
+    /// `nx`, `ny` and `nz` properties.
+    pub normals: Option<Vec<[f32; 3]>>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Scalar {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> io::Result<Scalar> {
+        match name {
+            "char" | "int8" => Ok(Scalar::Int8),
+            "uchar" | "uint8" => Ok(Scalar::UInt8),
+            "short" | "int16" => Ok(Scalar::Int16),
+            "ushort" | "uint16" => Ok(Scalar::UInt16),
+            "int" | "int32" => Ok(Scalar::Int32),
+            "uint" | "uint32" => Ok(Scalar::UInt32),
+            "float" | "float32" => Ok(Scalar::Float32),
+            "double" | "float64" => Ok(Scalar::Float64),
+            _ => Err(invalid_data(format!("unknown PLY type '{}'", name))),
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::Int8 | Scalar::UInt8 => 1,
+            Scalar::Int16 | Scalar::UInt16 => 2,
+            Scalar::Int32 | Scalar::UInt32 | Scalar::Float32 => 4,
+            Scalar::Float64 => 8,
+        }
+    }
+
+    fn is_float(self) -> bool {
+        self == Scalar::Float32 || self == Scalar::Float64
+    }
+}
+
+struct Property {
+    name: String,
+    /// Type of the length of a list property.
+    count: Option<Scalar>,
+    value: Scalar,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn find(&self, name: &str) -> Option<usize> {
+        self.properties.iter().position(|property| property.name == name)
+    }
+}
+
+/// Source of the values of the body, whatever its format.
+trait Values {
+    fn read(&mut self, scalar: Scalar) -> io::Result<f64>;
+}
+
+struct AsciiValues<R: BufRead> {
+    reader: R,
+    tokens: VecDeque<String>,
+}
+
+impl<R: BufRead> Values for AsciiValues<R> {
+    fn read(&mut self, _: Scalar) -> io::Result<f64> {
+        while self.tokens.is_empty() {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(invalid_data("unexpected end of PLY data"));
+            }
+            self.tokens.extend(line.split_whitespace().map(str::to_string));
+        }
+        let token = self.tokens.pop_front().unwrap();
+        token.parse().map_err(|_| invalid_data(format!("invalid PLY value '{}'", token)))
+    }
+}
+
+struct BinaryValues<R: Read> {
+    reader: R,
+    big_endian: bool,
+}
+
+impl<R: Read> Values for BinaryValues<R> {
+    fn read(&mut self, scalar: Scalar) -> io::Result<f64> {
+        let mut bytes = [0u8; 8];
+        let size = scalar.size();
+        self.reader.read_exact(&mut bytes[..size])?;
+        if self.big_endian {
+            bytes[..size].reverse();
+        }
+        let value = match scalar {
+            Scalar::Int8 => f64::from(bytes[0] as i8),
+            Scalar::UInt8 => f64::from(bytes[0]),
+            Scalar::Int16 => f64::from(i16::from_le_bytes([bytes[0], bytes[1]])),
+            Scalar::UInt16 => f64::from(u16::from_le_bytes([bytes[0], bytes[1]])),
+            Scalar::Int32 => f64::from(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            Scalar::UInt32 => f64::from(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            Scalar::Float32 => f64::from(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            Scalar::Float64 => f64::from_le_bytes(bytes),
+        };
+        Ok(value)
+    }
+}
+
+/// Writes values in the chosen format, one element per line in ASCII.
+struct ValueWriter<'a, W: Write + 'a> {
+    writer: &'a mut W,
+    format: PlyFormat,
+    first: bool,
+}
+
+impl<'a, W: Write> ValueWriter<'a, W> {
+    fn write(&mut self, scalar: Scalar, value: f64) -> io::Result<()> {
+        if self.format == PlyFormat::Ascii {
+            if !self.first {
+                write!(self.writer, " ")?;
+            }
+            self.first = false;
+            return match scalar {
+                Scalar::Float32 => write!(self.writer, "{}", value as f32),
+                Scalar::Float64 => write!(self.writer, "{}", value),
+                _ => write!(self.writer, "{}", value as i64),
+            };
+        }
+
+        let mut bytes = match scalar {
+            Scalar::Int8 => vec![value as i8 as u8],
+            Scalar::UInt8 => vec![value as u8],
+            Scalar::Int16 => (value as i16).to_le_bytes().to_vec(),
+            Scalar::UInt16 => (value as u16).to_le_bytes().to_vec(),
+            Scalar::Int32 => (value as i32).to_le_bytes().to_vec(),
+            Scalar::UInt32 => (value as u32).to_le_bytes().to_vec(),
+            Scalar::Float32 => (value as f32).to_le_bytes().to_vec(),
+            Scalar::Float64 => value.to_le_bytes().to_vec(),
+        };
+        if self.format == PlyFormat::BinaryBigEndian {
+            bytes.reverse();
+        }
+        self.writer.write_all(&bytes)
+    }
+
+    fn end(&mut self) -> io::Result<()> {
+        self.first = true;
+        if self.format == PlyFormat::Ascii {
+            writeln!(self.writer)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_header<R: BufRead>(reader: &mut R) -> io::Result<(PlyFormat, Vec<Element>)> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut line = String::new();
+    let mut first = true;
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_data("PLY header has no end_header line"));
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if first {
+            if tokens != ["ply"] {
+                return Err(invalid_data("not a PLY file"));
+            }
+            first = false;
+            continue;
+        }
+
+        match tokens.first().cloned() {
+            Some("format") => {
+                format = Some(match tokens.get(1).cloned() {
+                    Some("ascii") => PlyFormat::Ascii,
+                    Some("binary_little_endian") => PlyFormat::BinaryLittleEndian,
+                    Some("binary_big_endian") => PlyFormat::BinaryBigEndian,
+                    _ => return Err(invalid_data(format!("unknown PLY format '{}'", line.trim()))),
+                });
+            }
+            Some("element") if tokens.len() == 3 => {
+                let count = tokens[2].parse().map_err(|_| invalid_data(format!("invalid element count '{}'", line.trim())))?;
+                elements.push(Element { name: tokens[1].to_string(), count, properties: Vec::new() });
+            }
+            Some("property") => {
+                let element = elements.last_mut().ok_or_else(|| invalid_data("PLY property before any element"))?;
+                let property = match tokens.len() {
+                    3 => Property { name: tokens[2].to_string(), count: None, value: Scalar::parse(tokens[1])? },
+                    5 if tokens[1] == "list" => Property {
+                        name: tokens[4].to_string(),
+                        count: Some(Scalar::parse(tokens[2])?),
+                        value: Scalar::parse(tokens[3])?,
+                    },
+                    _ => return Err(invalid_data(format!("invalid PLY property '{}'", line.trim()))),
+                };
+                element.properties.push(property);
+            }
+            Some("end_header") => break,
+            Some("comment") | Some("obj_info") | None => {}
+            _ => return Err(invalid_data(format!("invalid PLY header line '{}'", line.trim()))),
+        }
+    }
+
+    let format = format.ok_or_else(|| invalid_data("PLY header has no format line"))?;
+    Ok((format, elements))
+}
+
+fn color_component(scalar: Scalar, value: f64) -> u8 {
+    let value = if scalar.is_float() { value * 255. } else { value };
+    value.clamp(0., 255.).round() as u8
+}
+
+fn read_body<T: Pos, V: Values>(values: &mut V, elements: &[Element]) -> io::Result<(Polyhedron<T>, VertexAttributes)> {
+    let mut positions = Vec::new();
+    let mut faces = Vec::new();
+    let mut colors = Vec::new();
+    let mut normals = Vec::new();
+    let mut has_colors = false;
+    let mut has_normals = false;
+
+    for element in elements {
+        let coordinates: Vec<Option<usize>> = ["x", "y", "z"].iter().map(|name| element.find(name)).collect();
+        let normal: Vec<Option<usize>> = ["nx", "ny", "nz"].iter().map(|name| element.find(name)).collect();
+        let color: Vec<Option<usize>> = ["red", "green", "blue", "alpha"].iter().map(|name| element.find(name)).collect();
+        let indices = element.find("vertex_indices").or_else(|| element.find("vertex_index"));
+
+        if element.name == "vertex" {
+            if coordinates[..T::dimension().min(3)].iter().any(Option::is_none) {
+                return Err(invalid_data("PLY vertices have too few coordinates"));
+            }
+            has_normals = normal.iter().all(Option::is_some);
+            has_colors = color[..3].iter().all(Option::is_some);
+        }
+
+        for _ in 0..element.count {
+            let mut row: Vec<Vec<f64>> = Vec::with_capacity(element.properties.len());
+            for property in &element.properties {
+                let mut list = Vec::new();
+                match property.count {
+                    Some(count) => {
+                        let size = values.read(count)?;
+                        for _ in 0..(size as usize) {
+                            list.push(values.read(property.value)?);
+                        }
+                    }
+                    None => list.push(values.read(property.value)?),
+                }
+                row.push(list);
+            }
+            let value = |index: usize| row[index].first().cloned().unwrap_or(0.);
+
+            if element.name == "vertex" {
+                let position: Vec<f32> = coordinates.iter().filter_map(|c| c.map(|c| value(c) as f32)).collect();
+                positions.push(T::from_coordinates(&position));
+                if has_normals {
+                    normals.push([value(normal[0].unwrap()) as f32, value(normal[1].unwrap()) as f32, value(normal[2].unwrap()) as f32]);
+                }
+                if has_colors {
+                    let mut rgba = [255u8; 4];
+                    for (k, c) in color.iter().enumerate() {
+                        if let Some(c) = *c {
+                            rgba[k] = color_component(element.properties[c].value, value(c));
+                        }
+                    }
+                    colors.push(rgba);
+                }
+            } else if element.name == "face" {
+                let indices = indices.ok_or_else(|| invalid_data("PLY faces have no vertex_indices property"))?;
+                let face = row[indices].iter()
+                    .map(|index| if *index >= 0. { Ok(*index as usize) } else { Err(invalid_data(format!("invalid face index {}", index))) })
+                    .collect::<io::Result<Vec<usize>>>()?;
+                faces.push(face);
+            }
+        }
+    }
+
+    let poly = Polyhedron::from_faces(&positions, &faces).map_err(invalid_data)?;
+    let attributes = VertexAttributes {
+        colors: if has_colors { Some(colors) } else { None },
+        normals: if has_normals { Some(normals) } else { None },
+    };
+    Ok((poly, attributes))
+}
+
+impl<T: Pos> Polyhedron<T> {
+    /// Writes the mesh as PLY with no vertex properties besides the position.
+    pub fn write_ply<W: Write>(&self, writer: &mut W, format: PlyFormat) -> io::Result<()> {
+        self.write_ply_with_attributes(writer, format, &VertexAttributes::default())
+    }
+
+    /// Writes the mesh as PLY, with the colours and normals given in `attributes`.
+    /// Missing coordinates are written as 0.
+    pub fn write_ply_with_attributes<W: Write>(&self, writer: &mut W, format: PlyFormat, attributes: &VertexAttributes) -> io::Result<()> {
+        let too_short = |length: usize| self.vertices.ids().any(|id| id.index() >= length);
+        if attributes.colors.as_ref().is_some_and(|colors| too_short(colors.len()))
+            || attributes.normals.as_ref().is_some_and(|normals| too_short(normals.len())) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "vertex attributes do not cover every vertex"));
+        }
+
+        writeln!(writer, "ply")?;
+        writeln!(writer, "format {} 1.0", match format {
+            PlyFormat::Ascii => "ascii",
+            PlyFormat::BinaryLittleEndian => "binary_little_endian",
+            PlyFormat::BinaryBigEndian => "binary_big_endian",
+        })?;
+        writeln!(writer, "element vertex {}", self.vertices_size())?;
+        for name in &["x", "y", "z"] {
+            writeln!(writer, "property float {}", name)?;
+        }
+        if attributes.normals.is_some() {
+            for name in &["nx", "ny", "nz"] {
+                writeln!(writer, "property float {}", name)?;
+            }
+        }
+        if attributes.colors.is_some() {
+            for name in &["red", "green", "blue", "alpha"] {
+                writeln!(writer, "property uchar {}", name)?;
+            }
+        }
+        writeln!(writer, "element face {}", self.facets_size())?;
+        writeln!(writer, "property list uchar int vertex_indices")?;
+        writeln!(writer, "end_header")?;
+
+        let mut values = ValueWriter { writer, format, first: true };
+        let mut indices = vec![0usize; self.vertices.capacity()];
+        for (i, (id, vertex)) in self.vertices.iter().enumerate() {
+            indices[id.index()] = i;
+            for k in 0..3 {
+                let coordinate = if k < T::dimension() { vertex.position[k] } else { 0. };
+                values.write(Scalar::Float32, f64::from(coordinate))?;
+            }
+            if let Some(ref normals) = attributes.normals {
+                for coordinate in &normals[id.index()] {
+                    values.write(Scalar::Float32, f64::from(*coordinate))?;
+                }
+            }
+            if let Some(ref colors) = attributes.colors {
+                for channel in &colors[id.index()] {
+                    values.write(Scalar::UInt8, f64::from(*channel))?;
+                }
+            }
+            values.end()?;
+        }
+
+        for facet in self.facets.ids() {
+            let degree = self.facet_vertices(facet).count();
+            if degree > 255 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{:?} has more than 255 vertices", facet)));
+            }
+            values.write(Scalar::UInt8, degree as f64)?;
+            for vertex in self.facet_vertices(facet) {
+                values.write(Scalar::Int32, indices[vertex.index()] as f64)?;
+            }
+            values.end()?;
+        }
+        Ok(())
+    }
+
+    pub fn save_as_ply(&self, path: &str, format: PlyFormat) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_ply(&mut writer, format)?;
+        writer.flush()
+    }
+
+    /// Reads a PLY mesh, leaving out vertex colours and normals.
+    pub fn read_ply<R: BufRead>(reader: R) -> io::Result<Polyhedron<T>> {
+        Polyhedron::read_ply_with_attributes(reader).map(|(poly, _)| poly)
+    }
+
+    /// Reads a PLY mesh with its vertex colours and normals. Vertex ids are the
+    /// indices of the vertices in the file.
+    pub fn read_ply_with_attributes<R: BufRead>(mut reader: R) -> io::Result<(Polyhedron<T>, VertexAttributes)> {
+        let (format, elements) = read_header(&mut reader)?;
+        match format {
+            PlyFormat::Ascii => read_body(&mut AsciiValues { reader, tokens: VecDeque::new() }, &elements),
+            PlyFormat::BinaryLittleEndian => read_body(&mut BinaryValues { reader, big_endian: false }, &elements),
+            PlyFormat::BinaryBigEndian => read_body(&mut BinaryValues { reader, big_endian: true }, &elements),
+        }
+    }
+
+    pub fn load_ply(path: &str) -> io::Result<Polyhedron<T>> {
+        Polyhedron::read_ply(BufReader::new(File::open(path)?))
+    }
+}
@@ -1,25 +1,26 @@
-use std::rc::Rc;
-use std::fs::File;
-use std::io::Write;
+use std::collections::HashMap;
 use std::f64::consts::*;
 
-use svg;
-use svg::Document;
-use svg::node::element::Path;
-use svg::node::element::path::Data;
-
+use arena::Arena;
+use error::{HalfEdgeError, MeshBuildError};
 use utils::*;
-use pos::{Pos, Pos2};
+use pos::{Pos, Pos2, Pos3};
+use predicates::{orient2d, Sign};
 use vertex::{Vertex, Vertex2};
-use halfedge::{HalfEdge, HalfEdge2};
-use facet::{Facet, Facet2};
-
-use std::vec::Vec;
+use halfedge::{self, HalfEdge};
+use circulator::VertexIncomingHalfEdges;
+use facet::Facet;
+use history::History;
+use property::PropertyMaps;
 
 pub struct Polyhedron<T: Pos> {
-    pub vertices: Vec<Handle<Vertex<T>>>,
-    pub edges: Vec<Handle<HalfEdge<T>>>,
-    pub facets: Vec<Handle<Facet<T>>>,
+    pub vertices: Arena<VertexId, Vertex<T>>,
+    pub edges: Arena<HalfEdgeId, HalfEdge>,
+    pub facets: Arena<FacetId, Facet>,
+    pub(crate) history: History<T>,
+    pub(crate) vertex_properties: PropertyMaps<VertexId>,
+    pub(crate) corner_properties: PropertyMaps<HalfEdgeId>,
+    pub(crate) facet_properties: PropertyMaps<FacetId>,
 }
 
 // Basic methods
@@ -27,9 +28,13 @@ pub struct Polyhedron<T: Pos> {
 impl<T: Pos> Polyhedron<T> {
     pub fn new() -> Polyhedron<T> {
         Polyhedron {
-            vertices: Vec::new(),
-            edges: Vec::new(),
-            facets: Vec::new(),
+            vertices: Arena::new(),
+            edges: Arena::new(),
+            facets: Arena::new(),
+            history: History::new(),
+            vertex_properties: PropertyMaps::new(),
+            corner_properties: PropertyMaps::new(),
+            facet_properties: PropertyMaps::new(),
         }
     }
 
@@ -48,352 +53,659 @@ impl<T: Pos> Polyhedron<T> {
 
 // Various constructors
 
+impl<T: Pos> Polyhedron<T> {
+    /// Builds a mesh from vertex positions and faces given as lists of
+    /// indices into `positions`, in counterclockwise order.
+    ///
+    /// The vertex built from `positions[i]` has id `VertexId::new(i)`.
+    /// Positions used by no face are dropped.
+    pub fn from_faces(positions: &[T], faces: &[Vec<usize>]) -> Result<Polyhedron<T>, MeshBuildError> {
+        let mut poly = Polyhedron::new();
+        let vertices: Vec<_> = positions.iter()
+            .map(|position| poly.vertices.insert(Vertex { position: *position, edge: None }))
+            .collect();
+
+        let mut directed_edges = HashMap::new();
+        let mut face_order = Vec::new();
+        let mut border_origins = vec![0usize; positions.len()];
+        let mut incoming = vec![0usize; positions.len()];
+
+        for (i, face) in faces.iter().enumerate() {
+            if let Some(&index) = face.iter().find(|&&index| index >= positions.len()) {
+                return Err(MeshBuildError::IndexOutOfBounds { face: i, index });
+            }
+            let mut sorted = face.clone();
+            sorted.sort();
+            sorted.dedup();
+            if face.len() < 3 || sorted.len() != face.len() {
+                return Err(MeshBuildError::DegenerateFace(i));
+            }
+
+            let f = poly.facets.insert(Facet::new());
+            let mut face_edges = Vec::with_capacity(face.len());
+            for k in 0..face.len() {
+                let (from, to) = (face[k], face[(k + 1) % face.len()]);
+                if directed_edges.contains_key(&(from, to)) {
+                    if directed_edges.contains_key(&(to, from)) {
+                        return Err(MeshBuildError::NonManifoldEdge(from, to));
+                    }
+                    return Err(MeshBuildError::InconsistentOrientation(from, to));
+                }
+                let e = poly.edges.insert(HalfEdge {
+                    vertex: Some(vertices[to]),
+                    face: Some(f),
+                    opposite: None,
+                    next: None,
+                });
+                poly.vertices[vertices[to]].edge = Some(e);
+                directed_edges.insert((from, to), e);
+                face_order.push((from, to));
+                incoming[to] += 1;
+                face_edges.push(e);
+            }
+            for k in 0..face_edges.len() {
+                poly.edges[face_edges[k]].next = Some(face_edges[(k + 1) % face_edges.len()]);
+            }
+            poly.facets[f].edge = Some(face_edges[0]);
+        }
+
+        // Going through the edges in face order, rather than in the order of
+        // the map, reports the same vertex every time.
+        for (from, to) in face_order {
+            let e = directed_edges[&(from, to)];
+            match directed_edges.get(&(to, from)) {
+                Some(&opposite) => poly.edges[e].opposite = Some(opposite),
+                None => {
+                    // The border twin of this edge goes from `to` back to `from`.
+                    border_origins[to] += 1;
+                    incoming[from] += 1;
+                    if border_origins[to] > 1 {
+                        return Err(MeshBuildError::NonManifoldVertex(to));
+                    }
+                }
+            }
+        }
+        poly.link_border();
+
+        for (i, vertex) in vertices.into_iter().enumerate() {
+            if incoming[i] == 0 {
+                poly.vertices.remove(vertex);
+                continue;
+            }
+            if poly.try_vertex_degree(vertex) != Ok(incoming[i] as i32) {
+                return Err(MeshBuildError::NonManifoldVertex(i));
+            }
+        }
+
+        Ok(poly)
+    }
+
+    // Gives every edge without an opposite a border twin with no face,
+    // and links the border twins into their own next cycles.
+    fn link_border(&mut self) {
+        let open_edges: Vec<_> = self.edges.iter()
+            .filter(|&(_, edge)| edge.opposite.is_none())
+            .map(|(id, _)| id)
+            .collect();
+
+        let mut outgoing = vec![None; self.vertices.capacity()];
+        let mut border_edges = Vec::with_capacity(open_edges.len());
+        for edge in open_edges {
+            let origin = get_element!(self.edges[self.get_prev_edge(edge)], vertex);
+            let border_edge = self.edges.insert(HalfEdge {
+                vertex: Some(origin),
+                face: None,
+                opposite: Some(edge),
+                next: None,
+            });
+            self.edges[edge].opposite = Some(border_edge);
+            outgoing[get_element!(self.edges[edge], vertex).index()] = Some(border_edge);
+            border_edges.push(border_edge);
+        }
+
+        for border_edge in border_edges {
+            let vertex = get_element!(self.edges[border_edge], vertex);
+            self.edges[border_edge].next = outgoing[vertex.index()];
+        }
+    }
+}
+
+/// Ids of the elements of a mesh in a copy made by `Polyhedron::deep_clone`.
+#[derive(Clone, Debug, Default)]
+pub struct ElementMap {
+    pub vertices: HashMap<VertexId, VertexId>,
+    pub edges: HashMap<HalfEdgeId, HalfEdgeId>,
+    pub facets: HashMap<FacetId, FacetId>,
+}
+
+impl<T: Pos> Polyhedron<T> {
+    /// Copies the mesh into new arenas, leaving out the removed slots, and
+    /// returns the copy with the id each element has in it. Links to removed
+    /// elements become `None`. The copy has the same property maps, and does
+    /// not record its operators.
+    pub fn deep_clone(&self) -> (Polyhedron<T>, ElementMap) {
+        let mut poly = Polyhedron {
+            vertices: Arena::with_capacity(self.vertices_size()),
+            edges: Arena::with_capacity(self.edges_size()),
+            facets: Arena::with_capacity(self.facets_size()),
+            history: History::new(),
+            vertex_properties: PropertyMaps::new(),
+            corner_properties: PropertyMaps::new(),
+            facet_properties: PropertyMaps::new(),
+        };
+        let mut map = ElementMap::default();
+        for (id, vertex) in self.vertices.iter() {
+            map.vertices.insert(id, poly.vertices.insert(Vertex { position: vertex.position, edge: None }));
+        }
+        for id in self.edges.ids() {
+            map.edges.insert(id, poly.edges.insert(HalfEdge::new()));
+        }
+        for id in self.facets.ids() {
+            map.facets.insert(id, poly.facets.insert(Facet::new()));
+        }
+
+        for (id, vertex) in self.vertices.iter() {
+            poly.vertices[map.vertices[&id]].edge = vertex.edge.and_then(|edge| map.edges.get(&edge).cloned());
+        }
+        for (id, edge) in self.edges.iter() {
+            let new_edge = &mut poly.edges[map.edges[&id]];
+            new_edge.vertex = edge.vertex.and_then(|vertex| map.vertices.get(&vertex).cloned());
+            new_edge.face = edge.face.and_then(|facet| map.facets.get(&facet).cloned());
+            new_edge.opposite = edge.opposite.and_then(|opposite| map.edges.get(&opposite).cloned());
+            new_edge.next = edge.next.and_then(|next| map.edges.get(&next).cloned());
+        }
+        for (id, facet) in self.facets.iter() {
+            poly.facets[map.facets[&id]].edge = facet.edge.and_then(|edge| map.edges.get(&edge).cloned());
+            poly.facets[map.facets[&id]].rings = facet.rings.iter().filter_map(|edge| map.edges.get(edge).cloned()).collect();
+        }
+        poly.vertex_properties = self.vertex_properties.remapped(&map.vertices);
+        poly.corner_properties = self.corner_properties.remapped(&map.edges);
+        poly.facet_properties = self.facet_properties.remapped(&map.facets);
+        (poly, map)
+    }
+}
+
 pub type Polyhedron2 = Polyhedron<Pos2>;
 impl Polyhedron2 {
     pub fn create_triangle() -> Polyhedron2 {
-        let v1 = new_handle(Vertex2 {
+        let mut poly = Polyhedron2::new();
+
+        let v1 = poly.vertices.insert(Vertex2 {
             edge: None,
             position: Pos2 { x: 0., y: 0. },
         });
-        let v2 = new_handle(Vertex2 {
+        let v2 = poly.vertices.insert(Vertex2 {
             edge: None,
             position: Pos2 { x: 0., y: 200. },
         });
-        let v3 = new_handle(Vertex2 {
+        let v3 = poly.vertices.insert(Vertex2 {
             edge: None,
             position: Pos2 { x: 200., y: 0. },
         });
 
-        let f = new_handle(Facet2::new());
+        let f = poly.facets.insert(Facet::new());
 
-        let e1 = new_handle(HalfEdge2 {
-            vertex: Some(Rc::clone(&v1)),
+        let e1 = poly.edges.insert(HalfEdge {
+            vertex: Some(v1),
             opposite: None,
             next: None,
-            face: Some(Rc::clone(&f)),
+            face: Some(f),
         });
-        let e3 = new_handle(HalfEdge2 {
-            vertex: Some(Rc::clone(&v3)),
+        let e3 = poly.edges.insert(HalfEdge {
+            vertex: Some(v3),
             opposite: None,
-            next: Some(Rc::clone(&e1)),
-            face: Some(Rc::clone(&f)),
+            next: Some(e1),
+            face: Some(f),
         });
-        let e2 = new_handle(HalfEdge2 {
-            vertex: Some(Rc::clone(&v2)),
+        let e2 = poly.edges.insert(HalfEdge {
+            vertex: Some(v2),
             opposite: None,
-            next: Some(Rc::clone(&e3)),
-            face: Some(Rc::clone(&f)),
+            next: Some(e3),
+            face: Some(f),
         });
-        e1.borrow_mut().next = Some(Rc::clone(&e2));
-
-        v1.borrow_mut().edge = Some(Rc::clone(&e1));
-        v2.borrow_mut().edge = Some(Rc::clone(&e2));
-        v3.borrow_mut().edge = Some(Rc::clone(&e3));
-
-        f.borrow_mut().edge = Some(Rc::clone(&e1));
-
-        let mut vertices = Vec::new();
-        vertices.push(v1);
-        vertices.push(v2);
-        vertices.push(v3);
+        poly.edges[e1].next = Some(e2);
 
-        let mut edges = Vec::new();
-        edges.push(e1);
-        edges.push(e2);
-        edges.push(e3);
+        poly.vertices[v1].edge = Some(e1);
+        poly.vertices[v2].edge = Some(e2);
+        poly.vertices[v3].edge = Some(e3);
 
-        let mut facets = Vec::new();
-        facets.push(f);
+        poly.facets[f].edge = Some(e1);
+        poly.link_border();
 
-        Polyhedron2 {
-            vertices: vertices,
-            edges: edges,
-            facets: facets,
-        }
+        poly
     }
 
     pub fn create_rectangle(corner_x: f32, corner_y: f32, height: f32, width: f32) -> Polyhedron2 {
-        let v1 = new_handle(Vertex2 {
+        let mut poly = Polyhedron2::new();
+
+        let v1 = poly.vertices.insert(Vertex2 {
             edge: None,
             position: Pos2 { x: corner_x, y: corner_y },
         });
-        let v2 = new_handle(Vertex2 {
+        let v2 = poly.vertices.insert(Vertex2 {
             edge: None,
             position: Pos2 { x: corner_x, y: corner_y + height },
         });
-        let v3 = new_handle(Vertex2 {
+        let v3 = poly.vertices.insert(Vertex2 {
             edge: None,
             position: Pos2 { x: corner_x + width, y: corner_y + height },
         });
-        let v4 = new_handle(Vertex2 {
+        let v4 = poly.vertices.insert(Vertex2 {
             edge: None,
             position: Pos2 { x: corner_x + width, y: corner_y },
         });
 
-        let f = new_handle(Facet2::new());
+        let f = poly.facets.insert(Facet::new());
 
-        let e1 = new_handle(HalfEdge2 {
-            vertex: Some(Rc::clone(&v1)),
+        let e1 = poly.edges.insert(HalfEdge {
+            vertex: Some(v1),
             opposite: None,
             next: None,
-            face: Some(Rc::clone(&f)),
+            face: Some(f),
         });
-        let e4 = new_handle(HalfEdge2 {
-            vertex: Some(Rc::clone(&v4)),
+        let e4 = poly.edges.insert(HalfEdge {
+            vertex: Some(v4),
             opposite: None,
-            next: Some(Rc::clone(&e1)),
-            face: Some(Rc::clone(&f)),
+            next: Some(e1),
+            face: Some(f),
         });
-        let e3 = new_handle(HalfEdge2 {
-            vertex: Some(Rc::clone(&v3)),
+        let e3 = poly.edges.insert(HalfEdge {
+            vertex: Some(v3),
             opposite: None,
-            next: Some(Rc::clone(&e4)),
-            face: Some(Rc::clone(&f)),
+            next: Some(e4),
+            face: Some(f),
         });
-        let e2 = new_handle(HalfEdge2 {
-            vertex: Some(Rc::clone(&v2)),
+        let e2 = poly.edges.insert(HalfEdge {
+            vertex: Some(v2),
             opposite: None,
-            next: Some(Rc::clone(&e3)),
-            face: Some(Rc::clone(&f)),
+            next: Some(e3),
+            face: Some(f),
         });
-        e1.borrow_mut().next = Some(Rc::clone(&e2));
-
-        v1.borrow_mut().edge = Some(Rc::clone(&e1));
-        v2.borrow_mut().edge = Some(Rc::clone(&e2));
-        v3.borrow_mut().edge = Some(Rc::clone(&e3));
-        v4.borrow_mut().edge = Some(Rc::clone(&e4));
-
-        f.borrow_mut().edge = Some(Rc::clone(&e1));
+        poly.edges[e1].next = Some(e2);
 
-        let mut vertices = Vec::new();
-        vertices.push(v1);
-        vertices.push(v2);
-        vertices.push(v3);
-        vertices.push(v4);
+        poly.vertices[v1].edge = Some(e1);
+        poly.vertices[v2].edge = Some(e2);
+        poly.vertices[v3].edge = Some(e3);
+        poly.vertices[v4].edge = Some(e4);
 
-        let mut edges = Vec::new();
-        edges.push(e1);
-        edges.push(e2);
-        edges.push(e3);
-        edges.push(e4);
+        poly.facets[f].edge = Some(e1);
+        poly.link_border();
 
-        let mut facets = Vec::new();
-        facets.push(f);
-
-        Polyhedron2 {
-            vertices: vertices,
-            edges: edges,
-            facets: facets,
-        }
+        poly
     }
 
     pub fn create_regular_polygon(center_x: f32, center_y: f32, radius: f32, sides: usize) -> Polyhedron2 {
-        let mut vertices = Vec::new();
-        let mut edges = Vec::new();
-        let mut facets = Vec::new();
+        let mut poly = Polyhedron2::new();
+        let mut edges = Vec::with_capacity(sides);
 
-        let f = new_handle(Facet2::new());
-        facets.push(f);
+        let f = poly.facets.insert(Facet::new());
 
         let angle = 2.0 * PI as f32 / (sides as f32);
         for i in 0..sides {
             let x = center_x + radius * ((i as f32 * angle).cos());
             let y = center_y - radius * ((i as f32 * angle).sin());
 
-            let vertex = Vertex2 {
+            let vertex = poly.vertices.insert(Vertex2 {
                 position: Pos2 {
                     x: x,
                     y: y,
                 },
                 edge: None
-            };
-            vertices.push(new_handle(vertex));
+            });
 
-            let mut edge = HalfEdge2::new();
-            edge.vertex = Some(Rc::clone(&vertices[i]));
-            edge.face = Some(Rc::clone(&facets[0]));
-            edges.push(new_handle(edge));
+            let mut edge = HalfEdge::new();
+            edge.vertex = Some(vertex);
+            edge.face = Some(f);
+            edges.push(poly.edges.insert(edge));
 
-            vertices[i].borrow_mut().edge = Some(Rc::clone(&edges[i]));
+            poly.vertices[vertex].edge = Some(edges[i]);
         }
 
         for i in 0..sides {
-            edges[i].borrow_mut().next = Some(Rc::clone(&edges[(i + 1) % sides]));
+            poly.edges[edges[i]].next = Some(edges[(i + 1) % sides]);
         }
 
-        facets[0].borrow_mut().edge = Some(Rc::clone(&edges[0]));
+        poly.facets[f].edge = Some(edges[0]);
+        poly.link_border();
 
-        Polyhedron2 {
-            vertices: vertices,
-            edges: edges,
-            facets: facets
-        }
+        poly
+    }
+}
+
+impl Polyhedron2 {
+    /// True if `edge` is flippable and the two triangles along it form a
+    /// strictly convex quadrilateral, so that the flip inverts no triangle.
+    pub fn is_flippable_convex(&self, edge: HalfEdgeId) -> bool {
+        self.try_flippable_convex(edge).is_ok()
+    }
+}
+
+pub type Polyhedron3 = Polyhedron<Pos3>;
+impl Polyhedron3 {
+    pub fn create_tetrahedron() -> Polyhedron3 {
+        let positions = [
+            Pos3 { x: 0., y: 0., z: 0. },
+            Pos3 { x: 100., y: 0., z: 0. },
+            Pos3 { x: 0., y: 100., z: 0. },
+            Pos3 { x: 0., y: 0., z: 100. },
+        ];
+        let faces = [vec![0, 2, 1], vec![0, 1, 3], vec![1, 2, 3], vec![0, 3, 2]];
+
+        Polyhedron3::from_faces(&positions, &faces).unwrap()
     }
 }
 
 // Utility operators
 
 impl<T: Pos> Polyhedron<T> {
-    pub fn get_center_position(&self, edge: Handle<HalfEdge<T>>) -> T {
-        let mut center = T::default();
-        let mut d = 0i32;
+    pub fn try_facet_degree(&self, facet: FacetId) -> Result<i32, HalfEdgeError> {
+        let edge = try_element!(self.facets, facet, edge, MissingEdge)?;
+        halfedge::cycle_length(&self.edges, edge)
+    }
 
-        let mut current_edge = Rc::clone(&edge);
-        while {
-            let v = get_element!(current_edge, vertex);
-            let pos = v.borrow().position;
+    pub fn try_vertex_degree(&self, vertex: VertexId) -> Result<i32, HalfEdgeError> {
+        let edge = try_element!(self.vertices, vertex, edge, MissingEdge)?;
+        halfedge::fan_length(&self.edges, edge)
+    }
+
+    pub(crate) fn try_vertex(&self, edge: HalfEdgeId) -> Result<VertexId, HalfEdgeError> {
+        let vertex = try_element!(self.edges, edge, vertex, MissingVertex)?;
+        if !self.vertices.contains(vertex) {
+            return Err(HalfEdgeError::RemovedElement(vertex.into()));
+        }
+        Ok(vertex)
+    }
 
-            center = center + pos;
-            d += 1;
+    // Returns the opposite of edge if both sides of it are facets.
+    fn try_interior(&self, edge: HalfEdgeId) -> Result<HalfEdgeId, HalfEdgeError> {
+        let opposite_edge = try_element!(self.edges, edge, opposite, MissingOpposite)?;
+        let opposite = self.edges.get(opposite_edge).ok_or(HalfEdgeError::RemovedElement(opposite_edge.into()))?;
+        if self.edges[edge].is_border() || opposite.is_border() {
+            return Err(HalfEdgeError::BoundaryEdge(edge));
+        }
+        Ok(opposite_edge)
+    }
 
-            current_edge = get_element!(current_edge, next);
-            edge != current_edge 
-        } {}
+    pub(crate) fn try_face(&self, edge: HalfEdgeId) -> Result<FacetId, HalfEdgeError> {
+        let face = try_element!(self.edges, edge, face, MissingFace)?;
+        if !self.facets.contains(face) {
+            return Err(HalfEdgeError::RemovedElement(face.into()));
+        }
+        Ok(face)
+    }
 
-        center = center / (d as f32);
+    /// First edges of the loops of `facet`, its own edge first.
+    pub(crate) fn try_facet_loops(&self, facet: FacetId) -> Result<Vec<HalfEdgeId>, HalfEdgeError> {
+        let facet = self.facets.get(facet).ok_or(HalfEdgeError::RemovedElement(facet.into()))?;
+        Ok(facet.edge.iter().chain(facet.rings.iter()).cloned().collect())
+    }
 
-        center
+    // The edge by which `facet` lists the loop `cycle`.
+    pub(crate) fn loop_start(&self, facet: FacetId, cycle: &[HalfEdgeId]) -> Option<HalfEdgeId> {
+        let facet = &self.facets[facet];
+        facet.edge.iter().chain(facet.rings.iter()).cloned().find(|start| cycle.contains(start))
     }
 
-    pub fn get_area(&self, edge: Handle<HalfEdge<T>>) -> f32 {
-        let mut area = 0f32;
+    // Lists the loop that `facet` reached by `old` with `new` instead, or
+    // drops it if `new` is `None`, the first ring then becoming the main loop.
+    pub(crate) fn replace_loop_start(&mut self, facet: FacetId, old: HalfEdgeId, new: Option<HalfEdgeId>) {
+        let facet = &mut self.facets[facet];
+        if facet.edge == Some(old) {
+            facet.edge = match new {
+                Some(new) => Some(new),
+                None if facet.rings.is_empty() => None,
+                None => Some(facet.rings.remove(0)),
+            };
+        } else if let Some(index) = facet.rings.iter().position(|ring| *ring == old) {
+            match new {
+                Some(new) => facet.rings[index] = new,
+                None => { facet.rings.remove(index); }
+            }
+        }
+    }
 
-        let first_vertex = get_element!(edge, vertex);
-        let first_position = first_vertex.borrow().position;
-        let mut current_edge = get_element!(edge, next);
-        let mut previous_vertex = get_element!(current_edge, vertex);
-        current_edge = get_element!(current_edge, next);
-        while edge != current_edge {
-            let vertex = get_element!(current_edge, vertex);
+    // Collects the next cycle starting at edge, checking that every edge in it has a vertex.
+    pub(crate) fn try_cycle(&self, edge: HalfEdgeId) -> Result<Vec<HalfEdgeId>, HalfEdgeError> {
+        let mut cycle = Vec::new();
+        let mut current_edge = edge;
+        while {
+            self.try_vertex(current_edge)?;
+            cycle.push(current_edge);
+            if cycle.len() > self.edges.len() {
+                return Err(HalfEdgeError::BrokenCycle(edge));
+            }
+            current_edge = try_element!(self.edges, current_edge, next, MissingNext)?;
+            current_edge != edge
+        } {}
+        Ok(cycle)
+    }
 
-            let previous_position = previous_vertex.borrow().position;
-            let current_position = vertex.borrow().position;
+    // Turns around the vertex of edge, returning the next edge pointing to that same vertex.
+    fn try_rotate(&self, edge: HalfEdgeId) -> Result<HalfEdgeId, HalfEdgeError> {
+        let next_edge = try_element!(self.edges, edge, next, MissingNext)?;
+        try_element!(self.edges, next_edge, opposite, MissingOpposite)
+    }
 
-            let ab = previous_position - first_position;
-            let ac = current_position - first_position;
+    // Turns around the vertex of from until reaching to.
+    fn try_rotate_until(&self, from: HalfEdgeId, to: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        let mut current_edge = from;
+        let mut count = 0;
+        while current_edge != to {
+            current_edge = self.try_rotate(current_edge)?;
+            count += 1;
+            if count > self.edges.len() {
+                return Err(HalfEdgeError::BrokenCycle(from));
+            }
+        }
+        Ok(())
+    }
 
-            let ab_norm = ab.norm();
-            let ac_norm = ac.norm();
+    // Collects the edges pointing to the vertex of edge, starting with edge.
+    pub(crate) fn try_fan(&self, edge: HalfEdgeId) -> Result<Vec<HalfEdgeId>, HalfEdgeError> {
+        let mut fan = Vec::new();
+        let mut current_edge = edge;
+        while {
+            fan.push(current_edge);
+            if fan.len() > self.edges.len() {
+                return Err(HalfEdgeError::BrokenCycle(edge));
+            }
+            current_edge = self.try_rotate(current_edge)?;
+            current_edge != edge
+        } {}
+        Ok(fan)
+    }
 
-            let cos_theta = (ab.dot(&ac)) / (ab_norm * ac_norm);
+    /// Average of the vertices of the loop of `edge`, which is not the
+    /// centroid of an irregular polygon.
+    pub fn get_center_position(&self, edge: HalfEdgeId) -> T {
+        self.try_get_center_position(edge).unwrap_or_else(|error| panic!("{}", error))
+    }
 
-            let triangle_area = ab_norm * ac_norm * (1f32 - cos_theta * cos_theta).sqrt();
+    pub fn try_get_center_position(&self, edge: HalfEdgeId) -> Result<T, HalfEdgeError> {
+        let degree = self.try_cycle(edge)?.len();
+        let center = self.loop_vertices(edge)
+            .fold(T::default(), |center, v| center + self.vertices[v].position);
 
-            area += triangle_area;
+        Ok(center / (degree as f32))
+    }
 
-            previous_vertex = vertex;
-            current_edge = get_element!(current_edge, next);
-        }
-        (area * 0.5f32).abs()
+    pub fn get_area(&self, edge: HalfEdgeId) -> f32 {
+        self.try_get_area(edge).unwrap_or_else(|error| panic!("{}", error))
     }
 
-    pub fn get_prev_edge(&self, edge: Handle<HalfEdge2>) -> Handle<HalfEdge2> {
-        let mut current_edge = get_element!(edge, next);
-        while(current_edge != edge) {
-            let next_edge = get_element!(current_edge, next);
-            if (next_edge == edge) {
-                return current_edge;
-            }
-            current_edge = next_edge;
+    pub fn try_get_area(&self, edge: HalfEdgeId) -> Result<f32, HalfEdgeError> {
+        self.try_cycle(edge)?;
+        let positions: Vec<T> = self.loop_vertices(edge).map(|v| self.vertices[v].position).collect();
+        let mut area = 0f64;
+
+        // Each triangle of the fan has area |ab||ac|sin(theta) / 2, and
+        // (|ab||ac|sin(theta))^2 = |ab|^2 |ac|^2 - (ab.ac)^2, evaluated in f64.
+        let dot = |u: &T, v: &T| (0..T::dimension()).map(|k| f64::from(u[k]) * f64::from(v[k])).sum::<f64>();
+        let first_position = positions[0];
+        for i in 2..positions.len() {
+            let ab = positions[i - 1] - first_position;
+            let ac = positions[i] - first_position;
+            let squared = dot(&ab, &ab) * dot(&ac, &ac) - dot(&ab, &ac) * dot(&ab, &ac);
+            area += squared.max(0.).sqrt();
         }
-        edge
+        Ok((area * 0.5) as f32)
+    }
+
+    pub fn get_prev_edge(&self, edge: HalfEdgeId) -> HalfEdgeId {
+        self.try_get_prev_edge(edge).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_get_prev_edge(&self, edge: HalfEdgeId) -> Result<HalfEdgeId, HalfEdgeError> {
+        let cycle = self.try_cycle(edge)?;
+        Ok(cycle[cycle.len() - 1])
     }
 }
 
 // Combinatorial Euler operators
 
-impl Polyhedron2 {
-    pub fn create_center_vertex(&mut self, edge: Handle<HalfEdge2>) {
-        let center_position = self.get_center_position(Rc::clone(&edge));
-        let vertex_handle = new_handle(Vertex2 { position: center_position, edge: None});
+impl<T: Pos> Polyhedron<T> {
+    pub fn create_center_vertex(&mut self, edge: HalfEdgeId) -> VertexId {
+        self.try_create_center_vertex(edge).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_create_center_vertex(&mut self, edge: HalfEdgeId) -> Result<VertexId, HalfEdgeError> {
+        self.record(|poly| poly.apply_create_center_vertex(edge, None))
+    }
 
-        let mut new_facets = Vec::new();
-        let mut new_edges = Vec::new();
+    /// Like `create_center_vertex`, with the new vertex at `position`, such as
+    /// the centroid of the facet.
+    pub fn create_center_vertex_at(&mut self, edge: HalfEdgeId, position: T) -> VertexId {
+        self.try_create_center_vertex_at(edge, position).unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        let facet_handle = get_element!(edge, face);
-        let degree = facet_handle.borrow().degree();
-        let mut current_edge = get_element!(facet_handle, edge);
+    pub fn try_create_center_vertex_at(&mut self, edge: HalfEdgeId, position: T) -> Result<VertexId, HalfEdgeError> {
+        self.record(|poly| poly.apply_create_center_vertex(edge, Some(position)))
+    }
 
+    fn apply_create_center_vertex(&mut self, edge: HalfEdgeId, position: Option<T>) -> Result<VertexId, HalfEdgeError> {
+        let facet = self.try_face(edge)?;
+        let facet_edge = try_element!(self.facets, facet, edge, MissingEdge)?;
+        let rim = self.try_cycle(facet_edge)?;
+        let center_position = match position {
+            Some(position) => position,
+            None => self.try_get_center_position(edge)?,
+        };
+
+        let vertex = self.vertices.insert(Vertex { position: center_position, edge: None });
+
+        let degree = self.facets[facet].degree(&self.edges) as usize;
+        let mut current_edge = get_element!(self.facets[facet], edge);
+
+        let mut new_facets = Vec::with_capacity(degree);
+        let mut new_edges = Vec::with_capacity(2 * degree);
+        new_facets.push(facet);
+        for _i in 1..degree {
+            new_facets.push(self.facets.insert(Facet::new()));
+        }
         for _i in 0..degree {
-            new_facets.push(new_handle(Facet2::new()));
-            new_edges.push(new_handle(HalfEdge2::new()));
-            new_edges.push(new_handle(HalfEdge2::new()));
+            new_edges.push(self.edges.insert(HalfEdge::new()));
+            new_edges.push(self.edges.insert(HalfEdge::new()));
         }
-        new_facets[0] = facet_handle;
 
-        for i in 0usize..degree as usize {
-            new_facets[i].borrow_mut().edge = Some(Rc::clone(&current_edge));
+        for i in 0..degree {
+            self.facets[new_facets[i]].edge = Some(current_edge);
 
-            let next_edge = get_element!(current_edge, next);
+            let next_edge = get_element!(self.edges[current_edge], next);
+            let current_vertex = get_element!(self.edges[current_edge], vertex);
 
             {
-                let mut edge_mut = current_edge.borrow_mut();
-                edge_mut.next = Some(Rc::clone(&new_edges[(2 * i)]));
-                edge_mut.face = Some(Rc::clone(&new_facets[i]));
+                let edge_mut = &mut self.edges[current_edge];
+                edge_mut.next = Some(new_edges[2 * i]);
+                edge_mut.face = Some(new_facets[i]);
             }
 
             {
-                let mut edge_mut = new_edges[(2 * i)].borrow_mut();
-                edge_mut.next = Some(Rc::clone(&new_edges[(2 * degree as usize + 2 * i - 1) %
-                                               (2 * degree as usize)]));
-                edge_mut.opposite = Some(Rc::clone(&new_edges[(2 * i + 1)]));
-                edge_mut.face = Some(Rc::clone(&new_facets[i]));
-                edge_mut.vertex = Some(Rc::clone(&vertex_handle));
+                let edge_mut = &mut self.edges[new_edges[2 * i]];
+                edge_mut.next = Some(new_edges[(2 * degree + 2 * i - 1) % (2 * degree)]);
+                edge_mut.opposite = Some(new_edges[2 * i + 1]);
+                edge_mut.face = Some(new_facets[i]);
+                edge_mut.vertex = Some(vertex);
             }
 
             {
-                let mut edge_mut = new_edges[(2 * i + 1)].borrow_mut();
-                edge_mut.next = Some(Rc::clone(&next_edge));
-                edge_mut.opposite = Some(Rc::clone(&new_edges[(2 * i)]));
-                edge_mut.face = Some(Rc::clone(&new_facets[(i + 1) % degree as usize]));
-                edge_mut.vertex = Some(get_element!(current_edge, vertex));
+                let edge_mut = &mut self.edges[new_edges[2 * i + 1]];
+                edge_mut.next = Some(next_edge);
+                edge_mut.opposite = Some(new_edges[2 * i]);
+                edge_mut.face = Some(new_facets[(i + 1) % degree]);
+                edge_mut.vertex = Some(current_vertex);
             }
 
             current_edge = next_edge;
         }
 
-        vertex_handle.borrow_mut().edge = Some(Rc::clone(&new_edges[0]));
+        self.vertices[vertex].edge = Some(new_edges[0]);
 
-        self.vertices.push(vertex_handle);
-        new_facets.remove(0);
-        for f in new_facets {
-            self.facets.push(f);
-        }
-        for e in new_edges {
-            self.edges.push(e);
+        // The center and its corners blend the vertices and corners of the
+        // facet. The new facets and rim corners copy those they split.
+        let weight = 1. / degree as f32;
+        let rim_vertices: Vec<_> = rim.iter().map(|e| (get_element!(self.edges[*e], vertex), weight)).collect();
+        let rim_corners: Vec<_> = rim.iter().map(|e| (*e, weight)).collect();
+        self.vertex_properties.interpolate(vertex, &rim_vertices);
+        for i in 0..degree {
+            self.corner_properties.interpolate(new_edges[2 * i], &rim_corners);
+            self.corner_properties.interpolate(new_edges[2 * i + 1], &[(rim[i], 1.)]);
+            self.facet_properties.interpolate(new_facets[i], &[(facet, 1.)]);
         }
+
+        Ok(vertex)
     }
 
-    pub fn erase_center_vertex(&mut self, edge: Handle<HalfEdge2>) {
-        let face = get_element!(edge, face);
-        let vertex = get_element!(edge, vertex);
-        let degree = vertex.borrow().degree();
+    pub fn erase_center_vertex(&mut self, edge: HalfEdgeId) {
+        self.try_erase_center_vertex(edge).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_erase_center_vertex(&mut self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        self.record(|poly| poly.apply_erase_center_vertex(edge))
+    }
+
+    fn apply_erase_center_vertex(&mut self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        let face = self.try_face(edge)?;
+        let vertex = self.try_vertex(edge)?;
+        let degree = halfedge::fan_length(&self.edges, edge)?;
+
+        let mut current_edge = edge;
+        for _i in 0..degree {
+            if self.edges[current_edge].is_border() {
+                return Err(HalfEdgeError::BoundaryVertex(vertex));
+            }
+            self.try_face(current_edge)?;
+            self.try_cycle(current_edge)?;
+            let opposite_edge = try_element!(self.edges, current_edge, opposite, MissingOpposite)?;
+            try_element!(self.edges, opposite_edge, next, MissingNext)?;
+            current_edge = self.try_rotate(current_edge)?;
+        }
 
         let mut faces_to_remove = Vec::new();
         let mut edges_to_remove = Vec::new();
 
-        let mut current_edge = Rc::clone(&edge);
+        let mut current_edge = edge;
         for i in 0..degree {
-            let next_edge = get_element!(current_edge, next);
-            let opposite_next_edge = get_element!(next_edge, opposite);
-
-            let mut previous_edge;
-            let mut temp_edge = get_element!(next_edge, next);
-            while  {
-                temp_edge.borrow_mut().face = Some(Rc::clone(&face));
-                previous_edge = Rc::clone(&temp_edge);
-                temp_edge = get_element!(temp_edge, next);
-                temp_edge != current_edge
-            } {}
-            let opposite_edge = get_element!(current_edge, opposite);
-            let next_opposite_edge = get_element!(opposite_edge, next);
-            previous_edge.borrow_mut().next = Some(next_opposite_edge);
+            let next_edge = get_element!(self.edges[current_edge], next);
+            let opposite_next_edge = get_element!(self.edges[next_edge], opposite);
+
+            let outer_edges: Vec<_> = self.loop_halfedges(current_edge).skip(2).collect();
+            for outer_edge in &outer_edges {
+                self.edges[*outer_edge].face = Some(face);
+            }
+            let previous_edge = outer_edges[outer_edges.len() - 1];
+            let opposite_edge = get_element!(self.edges[current_edge], opposite);
+            let next_opposite_edge = get_element!(self.edges[opposite_edge], next);
+            self.edges[previous_edge].next = Some(next_opposite_edge);
+
+            // The outer vertex loses its edges towards the center.
+            let outer_vertex = get_element!(self.edges[previous_edge], vertex);
+            self.vertices[outer_vertex].edge = Some(previous_edge);
 
             if i == 0 {
-                face.borrow_mut().edge = Some(previous_edge);
+                self.facets[face].edge = Some(previous_edge);
             }
 
-            faces_to_remove.push(get_element!(current_edge, face));
+            faces_to_remove.push(get_element!(self.edges[current_edge], face));
             edges_to_remove.push(current_edge);
             edges_to_remove.push(next_edge);
 
@@ -402,303 +714,667 @@ impl Polyhedron2 {
         faces_to_remove.remove(0);
 
         for f in faces_to_remove {
-            for i in 0..self.facets.len() {
-                if self.facets[i] == f {
-                    self.facets.remove(i);
-                    break;
-                }
-            }
+            self.discard_facet(f);
         }
         for e in edges_to_remove {
-            for i in 0..self.edges.len() {
-                if self.edges[i] == e {
-                    self.edges.remove(i);
-                    break;
-                }
+            self.discard_edge(e);
+        }
+        self.discard_vertex(vertex);
+
+        Ok(())
+    }
+
+    pub fn flip_edge(&mut self, edge: HalfEdgeId) {
+        self.try_flip_edge(edge).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// True if `edge` lies between two triangles and flipping it joins two
+    /// vertices that do not share an edge yet. Planar meshes also need
+    /// `is_flippable_convex` for the flip to succeed.
+    pub fn is_flippable(&self, edge: HalfEdgeId) -> bool {
+        self.try_flippable(edge).is_ok()
+    }
+
+    fn try_flippable(&self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        let opposite_edge = self.try_interior(edge)?;
+        let face = self.try_face(edge)?;
+        let opposite_face = self.try_face(opposite_edge)?;
+        self.try_vertex(edge)?;
+        self.try_vertex(opposite_edge)?;
+
+        let cycle = self.try_cycle(edge)?;
+        if cycle.len() != 3 {
+            return Err(HalfEdgeError::NotTriangle(face));
+        }
+        let opposite_cycle = self.try_cycle(opposite_edge)?;
+        if opposite_cycle.len() != 3 {
+            return Err(HalfEdgeError::NotTriangle(opposite_face));
+        }
+
+        let apex = self.try_vertex(cycle[1])?;
+        let opposite_apex = self.try_vertex(opposite_cycle[1])?;
+        if apex == opposite_apex {
+            return Err(HalfEdgeError::NotFlippable(edge));
+        }
+        for incoming in self.try_fan(cycle[1])? {
+            let outgoing = try_element!(self.edges, incoming, opposite, MissingOpposite)?;
+            if self.edges.get(outgoing).and_then(|e| e.vertex) == Some(opposite_apex) {
+                return Err(HalfEdgeError::NotFlippable(edge));
             }
         }
+        Ok(())
+    }
+
+    // On planar meshes, also checks that the quadrilateral around `edge` is
+    // strictly convex, as flipping the diagonal of any other one inverts a
+    // triangle or makes a flat one.
+    fn try_flippable_convex(&self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        self.try_flippable(edge)?;
+        if T::dimension() != 2 {
+            return Ok(());
+        }
+        let opposite_edge = get_element!(self.edges[edge], opposite);
+        let position = |e: HalfEdgeId| self.vertices[get_element!(self.edges[e], vertex)].position;
+        let a = position(opposite_edge);
+        let b = position(edge);
+        let c = position(get_element!(self.edges[edge], next));
+        let d = position(get_element!(self.edges[opposite_edge], next));
+
+        // The quadrilateral is convex when its two diagonals cross.
+        let opposite_sides = |first: Sign, second: Sign| first != Sign::Zero && second == first.reversed();
+        if opposite_sides(orient2d(a, b, c), orient2d(a, b, d)) && opposite_sides(orient2d(c, d, a), orient2d(c, d, b)) {
+            Ok(())
+        } else {
+            Err(HalfEdgeError::NotFlippable(edge))
+        }
+    }
+
+    /// Turns `edge`, the diagonal of the two triangles along it, into the
+    /// other diagonal. On planar meshes the two triangles must form a strictly
+    /// convex quadrilateral, so that no triangle is inverted.
+    pub fn try_flip_edge(&mut self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        self.record(|poly| poly.apply_flip_edge(edge))
     }
 
-    pub fn flip_edge(&self, edge: Handle<HalfEdge2>) {
-        let face = get_element!(edge, face);
-        let vertex = get_element!(edge, vertex);
+    pub(crate) fn apply_flip_edge(&mut self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        self.try_flippable_convex(edge)?;
+        let opposite_edge = get_element!(self.edges[edge], opposite);
+
+        let face = get_element!(self.edges[edge], face);
+        let vertex = get_element!(self.edges[edge], vertex);
 
-        let opposite_edge = get_element!(edge, opposite);
-        let opposite_face = get_element!(opposite_edge, face);
-        let opposite_vertex = get_element!(opposite_edge, vertex);
+        let opposite_face = get_element!(self.edges[opposite_edge], face);
+        let opposite_vertex = get_element!(self.edges[opposite_edge], vertex);
 
-        assert_eq!(face.borrow().degree(), 3);
-        assert_eq!(opposite_face.borrow().degree(), 3);
+        let next_edge = get_element!(self.edges[edge], next);
+        let previous_edge = get_element!(self.edges[next_edge], next);
 
-        let next_edge = get_element!(edge, next);
-        let previous_edge = get_element!(next_edge, next);
+        let next_opposite_edge = get_element!(self.edges[opposite_edge], next);
+        let previous_opposite_edge = get_element!(self.edges[next_opposite_edge], next);
 
-        let next_opposite_edge = get_element!(opposite_edge, next);
-        let previous_opposite_edge = get_element!(next_opposite_edge, next);
+        let next_vertex = get_element!(self.edges[next_edge], vertex);
+        let next_opposite_vertex = get_element!(self.edges[next_opposite_edge], vertex);
 
         {
-            let mut edge_mut = edge.borrow_mut();
-            edge_mut.vertex = Some(get_element!(next_edge, vertex));
-            edge_mut.next = Some(Rc::clone(&previous_edge));
+            let edge_mut = &mut self.edges[edge];
+            edge_mut.vertex = Some(next_vertex);
+            edge_mut.next = Some(previous_edge);
         }
         {
-            let mut edge_mut = opposite_edge.borrow_mut();
-            edge_mut.vertex = Some(get_element!(next_opposite_edge, vertex));
-            edge_mut.next = Some(Rc::clone(&previous_opposite_edge));
+            let edge_mut = &mut self.edges[opposite_edge];
+            edge_mut.vertex = Some(next_opposite_vertex);
+            edge_mut.next = Some(previous_opposite_edge);
         }
 
         {
-            let mut edge_mut = next_edge.borrow_mut();
-            edge_mut.face = Some(Rc::clone(&opposite_face));
-            edge_mut.next = Some(Rc::clone(&opposite_edge));
+            let edge_mut = &mut self.edges[next_edge];
+            edge_mut.face = Some(opposite_face);
+            edge_mut.next = Some(opposite_edge);
         }
         {
-            let mut edge_mut = next_opposite_edge.borrow_mut();
-            edge_mut.face = Some(Rc::clone(&face));
-            edge_mut.next = Some(Rc::clone(&edge));
+            let edge_mut = &mut self.edges[next_opposite_edge];
+            edge_mut.face = Some(face);
+            edge_mut.next = Some(edge);
         }
 
-        previous_edge.borrow_mut().next = Some(Rc::clone(&next_opposite_edge));
-        previous_opposite_edge.borrow_mut().next = Some(Rc::clone(&next_edge));
+        self.edges[previous_edge].next = Some(next_opposite_edge);
+        self.edges[previous_opposite_edge].next = Some(next_edge);
+
+        self.facets[face].edge = Some(edge);
+        self.facets[opposite_face].edge = Some(opposite_edge);
+
+        self.vertices[vertex].edge = Some(previous_opposite_edge);
+        self.vertices[opposite_vertex].edge = Some(previous_edge);
 
-        face.borrow_mut().edge = Some(Rc::clone(&edge));
-        opposite_face.borrow_mut().edge = Some(Rc::clone(&opposite_edge));
+        // The flipped edges end at the apexes, whose corners they copy.
+        self.corner_properties.interpolate(edge, &[(next_edge, 1.)]);
+        self.corner_properties.interpolate(opposite_edge, &[(next_opposite_edge, 1.)]);
+
+        Ok(())
+    }
+
+    pub fn split_facet(&mut self, edge1: HalfEdgeId, edge2: HalfEdgeId) -> FacetId {
+        self.try_split_facet(edge1, edge2).unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        vertex.borrow_mut().edge = Some(Rc::clone(&previous_opposite_edge));
-        opposite_vertex.borrow_mut().edge = Some(Rc::clone(&previous_edge));
+    pub fn try_split_facet(&mut self, edge1: HalfEdgeId, edge2: HalfEdgeId) -> Result<FacetId, HalfEdgeError> {
+        self.record(|poly| poly.apply_split_facet(edge1, edge2))
     }
 
-    pub fn split_facet(&mut self, edge1: Handle<HalfEdge2>, edge2: Handle<HalfEdge2>) {
-        let face1 = get_element!(edge1, face);
-        assert!(face1 == get_element!(edge2, face));
-        let face2 = new_handle(Facet2::new());
+    fn apply_split_facet(&mut self, edge1: HalfEdgeId, edge2: HalfEdgeId) -> Result<FacetId, HalfEdgeError> {
+        let face1 = self.try_face(edge1)?;
+        let other_face = self.try_face(edge2)?;
+        if face1 != other_face {
+            return Err(HalfEdgeError::FacetMismatch(face1, other_face));
+        }
+        if edge1 == edge2 {
+            return Err(HalfEdgeError::SameEdge(edge1));
+        }
+        let cycle = self.try_cycle(edge1)?;
+        if !cycle.contains(&edge2) {
+            return Err(HalfEdgeError::DifferentLoops(edge1, edge2));
+        }
+
+        let vertex1 = self.try_vertex(edge1)?;
+        let vertex2 = self.try_vertex(edge2)?;
 
-        let vertex1 = get_element!(edge1, vertex);
-        let vertex2 = get_element!(edge2, vertex);
+        let face2 = self.facets.insert(Facet::new());
 
-        let new_edge1 = new_handle(HalfEdge2::new());
-        let new_edge2 = new_handle(HalfEdge2::new());
+        let new_edge1 = self.edges.insert(HalfEdge::new());
+        let new_edge2 = self.edges.insert(HalfEdge::new());
+
+        let next1 = get_element!(self.edges[edge1], next);
+        let next2 = get_element!(self.edges[edge2], next);
 
         {
-            let mut edge_mut = new_edge1.borrow_mut();
-            edge_mut.opposite = Some(Rc::clone(&new_edge2));
-            edge_mut.vertex = Some(Rc::clone(&vertex2));
-            edge_mut.face = Some(Rc::clone(&face1));
-            edge_mut.next = Some(get_element!(edge2, next));
+            let edge_mut = &mut self.edges[new_edge1];
+            edge_mut.opposite = Some(new_edge2);
+            edge_mut.vertex = Some(vertex2);
+            edge_mut.face = Some(face1);
+            edge_mut.next = Some(next2);
         }
         {
-            let mut edge_mut = new_edge2.borrow_mut();
-            edge_mut.opposite = Some(Rc::clone(&new_edge1));
-            edge_mut.vertex = Some(Rc::clone(&vertex1));
-            edge_mut.face = Some(Rc::clone(&face2));
-            edge_mut.next = Some(get_element!(edge1, next));
+            let edge_mut = &mut self.edges[new_edge2];
+            edge_mut.opposite = Some(new_edge1);
+            edge_mut.vertex = Some(vertex1);
+            edge_mut.face = Some(face2);
+            edge_mut.next = Some(next1);
         }
 
-        edge1.borrow_mut().next = Some(Rc::clone(&new_edge1));
-        edge2.borrow_mut().next = Some(Rc::clone(&new_edge2));
+        self.edges[edge1].next = Some(new_edge1);
+        self.edges[edge2].next = Some(new_edge2);
 
-        face1.borrow_mut().edge = Some(Rc::clone(&edge1));
-        face2.borrow_mut().edge = Some(Rc::clone(&edge2));
+        match self.loop_start(face1, &cycle) {
+            Some(start) => self.replace_loop_start(face1, start, Some(edge1)),
+            None => self.facets[face1].edge = Some(edge1),
+        }
+        self.facets[face2].edge = Some(edge2);
 
-        let mut current_edge1 = Rc::clone(&new_edge1);
-        while {
-            current_edge1.borrow_mut().face = Some(Rc::clone(&face1));
-            current_edge1 = get_element!(current_edge1, next);
-            current_edge1 != new_edge1
-        } {}
+        let loop1: Vec<_> = self.loop_halfedges(new_edge1).collect();
+        for current_edge1 in loop1 {
+            self.edges[current_edge1].face = Some(face1);
+        }
 
-        let mut current_edge2 = Rc::clone(&new_edge2);
-        while {
-            current_edge2.borrow_mut().face = Some(Rc::clone(&face2));
-            current_edge2 = get_element!(current_edge2, next);
-            current_edge2 != new_edge2
-        } {}
+        let loop2: Vec<_> = self.loop_halfedges(new_edge2).collect();
+        for current_edge2 in loop2 {
+            self.edges[current_edge2].face = Some(face2);
+        }
 
-        self.edges.push(new_edge1);
-        self.edges.push(new_edge2);
+        // Both halves keep the values of the split facet and its corners.
+        self.facet_properties.interpolate(face2, &[(face1, 1.)]);
+        self.corner_properties.interpolate(new_edge1, &[(edge2, 1.)]);
+        self.corner_properties.interpolate(new_edge2, &[(edge1, 1.)]);
 
-        self.facets.push(face2);
+        Ok(face2)
     }
 
-    pub fn join_facet(&mut self, edge: Handle<HalfEdge2>) {
-        let face = get_element!(edge, face);
-        let opposite_edge = get_element!(edge, opposite);
-        let opposite_face = get_element!(opposite_edge, face);
-
-        let mut previous_edge = Rc::clone(&edge);
-        let mut current_edge = Rc::clone(&edge);
-        while {
-            let next_edge = get_element!(current_edge, next);
-            previous_edge = current_edge;
-            current_edge = next_edge;
-            current_edge != edge
-        } {}
-
-        let mut previous_opposite_edge = Rc::clone(&opposite_edge);
-        let mut current_opposite_edge = Rc::clone(&opposite_edge);
-        while {
-            previous_opposite_edge.borrow_mut().face = Some(Rc::clone(&face));
-            let next_edge = get_element!(current_opposite_edge, next);
-            previous_opposite_edge = current_opposite_edge;
-            current_opposite_edge = next_edge;
-            current_opposite_edge != opposite_edge
-        } {}
+    pub fn join_facet(&mut self, edge: HalfEdgeId) {
+        self.try_join_facet(edge).unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        previous_edge.borrow_mut().next = Some(get_element!(opposite_edge, next));
-        previous_opposite_edge.borrow_mut().next = Some(get_element!(edge, next));
+    pub fn try_join_facet(&mut self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        self.record(|poly| poly.apply_join_facet(edge))
+    }
 
-        for i in 0..self.edges.len() {
-            if edge == self.edges[i] {
-                self.edges.remove(i);
-                break;
-            }
+    fn apply_join_facet(&mut self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        let opposite_edge = self.try_interior(edge)?;
+        let face = self.try_face(edge)?;
+        let opposite_face = self.try_face(opposite_edge)?;
+        if face == opposite_face {
+            return Err(HalfEdgeError::SameFacet(face));
         }
-        for i in 0..self.edges.len() {
-            if opposite_edge == self.edges[i] {
-                self.edges.remove(i);
-                break;
+        let cycle = self.try_cycle(edge)?;
+        let opposite_cycle = self.try_cycle(opposite_edge)?;
+
+        let previous_edge = cycle[cycle.len() - 1];
+        let previous_opposite_edge = opposite_cycle[opposite_cycle.len() - 1];
+
+        // The other loops of the removed facet become rings of the kept one.
+        let moved_rings: Vec<HalfEdgeId> = self.try_facet_loops(opposite_face)?.into_iter()
+            .filter(|start| !opposite_cycle.contains(start))
+            .collect();
+        for ring in &moved_rings {
+            let ring_edges: Vec<_> = self.loop_halfedges(*ring).collect();
+            for ring_edge in ring_edges {
+                self.edges[ring_edge].face = Some(face);
             }
         }
-        for i in 0..self.facets.len() {
-            if opposite_face == self.facets[i] {
-                self.facets.remove(i);
-                break;
-            }
+        for current_opposite_edge in opposite_cycle {
+            self.edges[current_opposite_edge].face = Some(face);
         }
+
+        let next_edge = get_element!(self.edges[edge], next);
+        let next_opposite_edge = get_element!(self.edges[opposite_edge], next);
+        self.edges[previous_edge].next = Some(next_opposite_edge);
+        self.edges[previous_opposite_edge].next = Some(next_edge);
+
+        match self.loop_start(face, &cycle) {
+            Some(start) => self.replace_loop_start(face, start, Some(next_edge)),
+            None => self.facets[face].edge = Some(next_edge),
+        }
+        self.facets[face].rings.extend(moved_rings);
+
+        let vertex = get_element!(self.edges[edge], vertex);
+        let opposite_vertex = get_element!(self.edges[opposite_edge], vertex);
+        self.vertices[vertex].edge = Some(previous_opposite_edge);
+        self.vertices[opposite_vertex].edge = Some(previous_edge);
+
+        self.discard_edge(edge);
+        self.discard_edge(opposite_edge);
+        self.discard_facet(opposite_face);
+
+        Ok(())
     }
 
-    pub fn split_vertex(&mut self, edge1: Handle<HalfEdge2>, edge2: Handle<HalfEdge2>) {
-        let vertex1 = get_element!(edge1, vertex);
-        let vertex2 = get_element!(edge2, vertex);
+    pub fn split_vertex(&mut self, edge1: HalfEdgeId, edge2: HalfEdgeId) -> VertexId {
+        self.try_split_vertex(edge1, edge2).unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        assert!(vertex1 == vertex2);
+    pub fn try_split_vertex(&mut self, edge1: HalfEdgeId, edge2: HalfEdgeId) -> Result<VertexId, HalfEdgeError> {
+        self.record(|poly| poly.apply_split_vertex(edge1, edge2))
+    }
 
-        let new_vertex = new_handle(Vertex2 { position: vertex1.borrow().position, edge: Some(Rc::clone(&edge2)) });
+    fn apply_split_vertex(&mut self, edge1: HalfEdgeId, edge2: HalfEdgeId) -> Result<VertexId, HalfEdgeError> {
+        let vertex1 = self.try_vertex(edge1)?;
+        let vertex2 = self.try_vertex(edge2)?;
 
-        let new_edge1 = new_handle(HalfEdge2 { vertex: Some(Rc::clone(&new_vertex)), face: Some(get_element!(edge1, face)), opposite: None, next: Some(get_element!(edge1, next)) });
-        let new_edge2 = new_handle(HalfEdge2 { vertex: Some(Rc::clone(&vertex1)), face: Some(get_element!(edge2, face)), opposite: Some(Rc::clone(&new_edge1)), next: Some(get_element!(edge2, next)) });
-        new_edge1.borrow_mut().opposite = Some(Rc::clone(&new_edge2));
+        if vertex1 != vertex2 {
+            return Err(HalfEdgeError::VertexMismatch(vertex1, vertex2));
+        }
+        if edge1 == edge2 {
+            return Err(HalfEdgeError::SameEdge(edge1));
+        }
+        try_element!(self.edges, edge2, next, MissingNext)?;
+        let first_edge = self.try_rotate(edge1)?;
+        self.try_rotate_until(first_edge, edge2)?;
+
+        let moved_edges: Vec<_> = VertexIncomingHalfEdges::new(&self.edges, Some(edge1))
+            .skip(1)
+            .take_while(|e| *e != edge2)
+            .collect();
+
+        let position = self.vertices[vertex1].position;
+        let new_vertex = self.vertices.insert(Vertex { position, edge: Some(edge2) });
 
-        let mut current_edge = get_element!(new_edge1, next);
-        current_edge = get_element!(current_edge, opposite);
-        while(current_edge != edge2) {
-            current_edge.borrow_mut().vertex = Some(Rc::clone(&new_vertex));
+        let new_edge1 = self.edges.insert(HalfEdge {
+            vertex: Some(new_vertex),
+            face: self.edges[edge1].face,
+            opposite: None,
+            next: self.edges[edge1].next,
+        });
+        let new_edge2 = self.edges.insert(HalfEdge {
+            vertex: Some(vertex1),
+            face: self.edges[edge2].face,
+            opposite: Some(new_edge1),
+            next: self.edges[edge2].next,
+        });
+        self.edges[new_edge1].opposite = Some(new_edge2);
 
-            current_edge = get_element!(current_edge, next);
-            current_edge = get_element!(current_edge, opposite);
+        for current_edge in moved_edges {
+            self.edges[current_edge].vertex = Some(new_vertex);
         }
 
-        edge1.borrow_mut().next = Some(Rc::clone(&new_edge1));
+        self.edges[edge1].next = Some(new_edge1);
 
-        edge2.borrow_mut().next = Some(Rc::clone(&new_edge2));
-        edge2.borrow_mut().vertex = Some(Rc::clone(&new_vertex));
+        self.edges[edge2].next = Some(new_edge2);
+        self.edges[edge2].vertex = Some(new_vertex);
 
-        self.vertices.push(new_vertex);
+        self.vertices[vertex1].edge = Some(edge1);
 
-        self.edges.push(new_edge1);
-        self.edges.push(new_edge2);
+        self.vertex_properties.interpolate(new_vertex, &[(vertex1, 1.)]);
+        self.corner_properties.interpolate(new_edge1, &[(edge1, 1.)]);
+        self.corner_properties.interpolate(new_edge2, &[(edge2, 1.)]);
+
+        Ok(new_vertex)
     }
 
-    pub fn join_vertex(&mut self, edge: Handle<HalfEdge2>) {
-        // This function will fail on edge vertices. 
-        // Need to figure out a way to make it work.
-        //
-        let opposite_edge = get_element!(edge, opposite);
+    pub fn split_edge(&mut self, edge: HalfEdgeId, t: f32) -> VertexId {
+        self.try_split_edge(edge, t).unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        let vertex = get_element!(edge, vertex);
-        let opposite_vertex = get_element!(opposite_edge, vertex);
+    /// Inserts a vertex on `edge`, at `t` from its origin (0) to its vertex (1).
+    /// The facets on both sides gain a vertex, and `edge` now ends at the new one.
+    /// A `t` outside of [0, 1], or NaN, is an error.
+    pub fn try_split_edge(&mut self, edge: HalfEdgeId, t: f32) -> Result<VertexId, HalfEdgeError> {
+        self.record(|poly| poly.apply_split_edge(edge, t))
+    }
 
-        let new_pos = 0.5f32 * (vertex.borrow().position + opposite_vertex.borrow().position);
-        vertex.borrow_mut().position = new_pos;
+    fn apply_split_edge(&mut self, edge: HalfEdgeId, t: f32) -> Result<VertexId, HalfEdgeError> {
+        if !(0. ..=1.).contains(&t) {
+            return Err(HalfEdgeError::InvalidSplit(edge));
+        }
+        let opposite_edge = try_element!(self.edges, edge, opposite, MissingOpposite)?;
+        let vertex = self.try_vertex(edge)?;
+        let opposite_vertex = self.try_vertex(opposite_edge)?;
+        let next_edge = try_element!(self.edges, edge, next, MissingNext)?;
+        let next_opposite_edge = try_element!(self.edges, opposite_edge, next, MissingNext)?;
+
+        let previous_edge = self.try_get_prev_edge(edge)?;
+        let previous_opposite_edge = self.try_get_prev_edge(opposite_edge)?;
+
+        let origin = self.vertices[opposite_vertex].position;
+        let position = origin + (self.vertices[vertex].position - origin) * t;
+        let new_vertex = self.vertices.insert(Vertex { position, edge: Some(edge) });
+
+        let new_edge = self.edges.insert(HalfEdge {
+            vertex: Some(vertex),
+            face: self.edges[edge].face,
+            opposite: Some(opposite_edge),
+            next: Some(next_edge),
+        });
+        let new_opposite_edge = self.edges.insert(HalfEdge {
+            vertex: Some(opposite_vertex),
+            face: self.edges[opposite_edge].face,
+            opposite: Some(edge),
+            next: Some(next_opposite_edge),
+        });
 
-        let face = get_element!(edge, face);
-        face.borrow_mut().edge = Some(get_element!(edge, next));
+        {
+            let edge_mut = &mut self.edges[edge];
+            edge_mut.vertex = Some(new_vertex);
+            edge_mut.opposite = Some(new_opposite_edge);
+            edge_mut.next = Some(new_edge);
+        }
+        {
+            let edge_mut = &mut self.edges[opposite_edge];
+            edge_mut.vertex = Some(new_vertex);
+            edge_mut.opposite = Some(new_edge);
+            edge_mut.next = Some(new_opposite_edge);
+        }
 
-        let opposite_face = get_element!(opposite_edge, face);
-        opposite_face.borrow_mut().edge = Some(get_element!(opposite_edge, next));
+        if self.vertices[vertex].edge == Some(edge) {
+            self.vertices[vertex].edge = Some(new_edge);
+        }
+        if self.vertices[opposite_vertex].edge == Some(opposite_edge) {
+            self.vertices[opposite_vertex].edge = Some(new_opposite_edge);
+        }
 
-        let previous_edge = self.get_prev_edge(Rc::clone(&edge));
-        let previous_opposite_edge = self.get_prev_edge(Rc::clone(&opposite_edge));
+        // The halves ending at the old vertices keep their corners, and the
+        // corners at the new vertex are blended along each side.
+        self.vertex_properties.interpolate(new_vertex, &[(opposite_vertex, 1. - t), (vertex, t)]);
+        self.corner_properties.interpolate(new_edge, &[(edge, 1.)]);
+        self.corner_properties.interpolate(new_opposite_edge, &[(opposite_edge, 1.)]);
+        self.corner_properties.interpolate(edge, &[(previous_edge, 1. - t), (edge, t)]);
+        self.corner_properties.interpolate(opposite_edge, &[(previous_opposite_edge, t), (opposite_edge, 1. - t)]);
 
-        let mut current_edge = Rc::clone(&opposite_edge);
-        while(current_edge != previous_edge) {
-            current_edge.borrow_mut().vertex = Some(Rc::clone(&vertex));
-            current_edge = get_element!(current_edge, next);
-            current_edge = get_element!(current_edge, opposite);
-        }
+        Ok(new_vertex)
+    }
 
-        previous_edge.borrow_mut().next = Some(get_element!(edge, next));
-        previous_edge.borrow_mut().vertex = Some(Rc::clone(&vertex));
+    pub fn split_edge_and_triangulate(&mut self, edge: HalfEdgeId, t: f32) -> VertexId {
+        self.try_split_edge_and_triangulate(edge, t).unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        previous_opposite_edge.borrow_mut().next = Some(get_element!(opposite_edge, next));
+    /// Splits `edge` as `split_edge`, then joins the new vertex to the third
+    /// vertex of the triangles on each side, leaving the border side alone.
+    pub fn try_split_edge_and_triangulate(&mut self, edge: HalfEdgeId, t: f32) -> Result<VertexId, HalfEdgeError> {
+        self.record(|poly| poly.apply_split_edge_and_triangulate(edge, t))
+    }
 
-        for i in 0..self.edges.len() {
-            if edge == self.edges[i] {
-                self.edges.remove(i);
-                break;
+    fn apply_split_edge_and_triangulate(&mut self, edge: HalfEdgeId, t: f32) -> Result<VertexId, HalfEdgeError> {
+        let opposite_edge = try_element!(self.edges, edge, opposite, MissingOpposite)?;
+        for side in &[edge, opposite_edge] {
+            if let Some(face) = self.edges.get(*side).and_then(|e| e.face) {
+                self.try_face(*side)?;
+                if self.try_cycle(*side)?.len() != 3 {
+                    return Err(HalfEdgeError::NotTriangle(face));
+                }
             }
         }
-        for i in 0..self.edges.len() {
-            if opposite_edge == self.edges[i] {
-                self.edges.remove(i);
-                break;
+
+        let new_vertex = self.try_split_edge(edge, t)?;
+        for side in &[edge, opposite_edge] {
+            if self.edges[*side].is_border() {
+                continue;
             }
+            let apex_edge = get_element!(self.edges[get_element!(self.edges[*side], next)], next);
+            self.try_split_facet(*side, apex_edge)?;
         }
-        for i in 0..self.vertices.len() {
-            if opposite_vertex == self.vertices[i] {
-                self.vertices.remove(i);
-                break;
-            }
+        Ok(new_vertex)
+    }
+
+    /// Merges the two ends of `edge` at its midpoint, with no check that the
+    /// mesh stays manifold. `collapse_edge` checks it first.
+    pub fn join_vertex(&mut self, edge: HalfEdgeId) {
+        self.try_join_vertex(edge).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_join_vertex(&mut self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        self.record(|poly| poly.apply_join_vertex(edge))
+    }
+
+    fn apply_join_vertex(&mut self, edge: HalfEdgeId) -> Result<(), HalfEdgeError> {
+        let opposite_edge = try_element!(self.edges, edge, opposite, MissingOpposite)?;
+
+        let vertex = self.try_vertex(edge)?;
+        let opposite_vertex = self.try_vertex(opposite_edge)?;
+
+        let cycle = self.try_cycle(edge)?;
+        self.try_cycle(opposite_edge)?;
+        self.try_rotate_until(opposite_edge, cycle[cycle.len() - 1])?;
+
+        let new_pos = (self.vertices[vertex].position + self.vertices[opposite_vertex].position) * 0.5f32;
+        self.vertices[vertex].position = new_pos;
+        self.vertex_properties.interpolate(vertex, &[(vertex, 0.5), (opposite_vertex, 0.5)]);
+
+        let next_edge = get_element!(self.edges[edge], next);
+        let next_opposite_edge = get_element!(self.edges[opposite_edge], next);
+
+        if let Some(face) = self.edges[edge].face {
+            self.facets[face].edge = Some(next_edge);
+        }
+        if let Some(opposite_face) = self.edges[opposite_edge].face {
+            self.facets[opposite_face].edge = Some(next_opposite_edge);
         }
 
+        let previous_edge = self.get_prev_edge(edge);
+        let previous_opposite_edge = self.get_prev_edge(opposite_edge);
+        self.corner_properties.interpolate(previous_edge, &[(previous_edge, 0.5), (edge, 0.5)]);
+        self.corner_properties.interpolate(previous_opposite_edge, &[(previous_opposite_edge, 0.5), (opposite_edge, 0.5)]);
 
+        let moved_edges: Vec<_> = VertexIncomingHalfEdges::new(&self.edges, Some(opposite_edge))
+            .take_while(|e| *e != previous_edge)
+            .collect();
+        for current_edge in moved_edges {
+            self.edges[current_edge].vertex = Some(vertex);
+        }
 
+        self.edges[previous_edge].next = Some(next_edge);
+        self.edges[previous_edge].vertex = Some(vertex);
 
+        self.edges[previous_opposite_edge].next = Some(next_opposite_edge);
 
+        self.vertices[vertex].edge = Some(previous_edge);
+
+        self.discard_edge(edge);
+        self.discard_edge(opposite_edge);
+        self.discard_vertex(opposite_vertex);
+
+        Ok(())
+    }
+
+    /// True if `collapse_edge` can merge the two ends of `edge` and keep a
+    /// manifold mesh.
+    ///
+    /// The ends must have no common neighbour besides the third vertex of the
+    /// triangles on each side of the edge, and no common facet besides the
+    /// ones along the edge. An inner edge cannot join two border vertices.
+    pub fn is_collapsible(&self, edge: HalfEdgeId) -> bool {
+        self.try_is_collapsible(edge).unwrap_or(false)
     }
-}
 
-// Display and output methods
+    fn try_is_collapsible(&self, edge: HalfEdgeId) -> Result<bool, HalfEdgeError> {
+        let opposite_edge = try_element!(self.edges, edge, opposite, MissingOpposite)?;
+        let vertex = self.try_vertex(edge)?;
+        let opposite_vertex = self.try_vertex(opposite_edge)?;
+        if vertex == opposite_vertex || (self.edges[edge].is_border() && self.edges[opposite_edge].is_border()) {
+            return Ok(false);
+        }
 
-impl Polyhedron2 {
-    pub fn draw_svg(&self, path: &str, height: u32, width: u32) {
-
-        let mut svg_document = Document::new()
-            .set("viewBox", (0, 0, 500, 500));
-        for f in &self.facets {
-            let degree = f.borrow().degree();
-            let mut current_edge = get_element!(f, edge);
-
-            let start_vertex = get_element!(current_edge, vertex);
-            let start_position = start_vertex.borrow().position;
-            let mut face_data = Data::new()
-                .move_to((start_position.x, start_position.y));
-
-            for _ in 0..degree {
-                //let first_vertex = get_element!(current_edge, vertex);
-                //let first_position = first_vertex.borrow().position;
-                let next_edge = get_element!(current_edge, next);
-                let second_vertex = get_element!(next_edge, vertex);
-                let second_position = second_vertex.borrow().position;
-                current_edge = next_edge;
-
-                face_data = face_data.line_by((second_position.x, second_position.y));
+        let fan = self.try_fan(edge)?;
+        let opposite_fan = self.try_fan(opposite_edge)?;
+        let is_border = |fan: &[HalfEdgeId]| fan.iter().any(|e| self.edges[*e].is_border());
+        if !self.is_border_edge(edge) && is_border(&fan) && is_border(&opposite_fan) {
+            return Ok(false);
+        }
+
+        // Triangles along the edge become two-edge loops, whose other edges get glued.
+        let sides = [self.try_cycle(edge)?, self.try_cycle(opposite_edge)?];
+        if sides[0].contains(&opposite_edge) {
+            return Ok(false);
+        }
+        let mut apexes = Vec::new();
+        for side in &sides {
+            if side.len() != 3 {
+                continue;
+            }
+            // Closing a three-edge hole would change the topology.
+            if self.edges[side[0]].is_border() {
+                return Ok(false);
+            }
+            let outer: Vec<HalfEdgeId> = side[1..].iter()
+                .map(|e| try_element!(self.edges, *e, opposite, MissingOpposite))
+                .collect::<Result<_, _>>()?;
+            // Only the border may run along the triangle on the other side.
+            let glued_to_side = sides.iter().any(|other| {
+                !self.edges[other[0]].is_border() && (other.contains(&outer[0]) || other.contains(&outer[1]))
+            });
+            if glued_to_side {
+                return Ok(false);
             }
-            face_data = face_data.close();
+            let (face0, face1) = (self.edges[outer[0]].face, self.edges[outer[1]].face);
+            if face0 == face1 {
+                return Ok(false);
+            }
+            apexes.push(self.try_vertex(side[1])?);
+        }
 
-            let face_path = Path::new()
-                .set("fill", "black")
-                .set("stroke", "red")
-                .set("stroke-width","3")
-                .set("d", face_data);
-            svg_document = svg_document.add(face_path);
+        let mut neighbors = Vec::new();
+        for e in &fan {
+            let opposite = try_element!(self.edges, *e, opposite, MissingOpposite)?;
+            neighbors.push(self.try_vertex(opposite)?);
+        }
+        for e in &opposite_fan {
+            let opposite = try_element!(self.edges, *e, opposite, MissingOpposite)?;
+            let neighbor = self.try_vertex(opposite)?;
+            if neighbor != vertex && neighbors.contains(&neighbor) && !apexes.contains(&neighbor) {
+                return Ok(false);
+            }
         }
 
-        svg::save(path, &svg_document).unwrap();
+        let sides_faces = [self.edges[edge].face, self.edges[opposite_edge].face];
+        let faces: Vec<FacetId> = fan.iter().filter_map(|e| self.edges[*e].face).collect();
+        let shares_face = opposite_fan.iter()
+            .filter_map(|e| self.edges[*e].face)
+            .any(|face| faces.contains(&face) && !sides_faces.contains(&Some(face)));
+        Ok(!shares_face)
     }
 
-    pub fn save_as_obj(&self, path: &str) {
+    pub fn collapse_edge(&mut self, edge: HalfEdgeId, position: T) -> VertexId {
+        self.try_collapse_edge(edge, position).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Merges the two ends of `edge` into its vertex, moved to `position`.
+    /// Triangles along the edge are removed, and the vertex is returned.
+    pub fn try_collapse_edge(&mut self, edge: HalfEdgeId, position: T) -> Result<VertexId, HalfEdgeError> {
+        self.record(|poly| poly.apply_collapse_edge(edge, position))
+    }
 
+    fn apply_collapse_edge(&mut self, edge: HalfEdgeId, position: T) -> Result<VertexId, HalfEdgeError> {
+        if !self.try_is_collapsible(edge)? {
+            return Err(HalfEdgeError::NotCollapsible(edge));
+        }
+        let opposite_edge = get_element!(self.edges[edge], opposite);
+        let vertex = get_element!(self.edges[edge], vertex);
+        let opposite_vertex = get_element!(self.edges[opposite_edge], vertex);
+
+        let fan = self.try_fan(edge)?;
+        let opposite_fan = self.try_fan(opposite_edge)?;
+        for current_edge in &opposite_fan {
+            self.edges[*current_edge].vertex = Some(vertex);
+        }
+        // Properties are blended by where `position` projects onto the edge.
+        let origin = self.vertices[opposite_vertex].position;
+        let (direction, offset) = (self.vertices[vertex].position - origin, position - origin);
+        let dot = |u: T, v: T| (0..T::dimension()).map(|k| f64::from(u[k]) * f64::from(v[k])).sum::<f64>();
+        let length = dot(direction, direction);
+        let t = if length > 0. { (dot(offset, direction) / length).clamp(0., 1.) as f32 } else { 0.5 };
+        self.vertices[vertex].position = position;
+        self.vertex_properties.interpolate(vertex, &[(opposite_vertex, 1. - t), (vertex, t)]);
+
+        // Each corner at one end blends with the corner at the other end in a
+        // facet along the edge. The corners at the end of `side` go first, as
+        // they read `previous_side`, while the others read `side`, which is
+        // removed.
+        let side = if self.edges[edge].is_border() { opposite_edge } else { edge };
+        let previous_side = self.get_prev_edge(side);
+        let (near, far, weight) = if side == edge { (&fan, &opposite_fan, t) } else { (&opposite_fan, &fan, 1. - t) };
+        for corner in near {
+            if *corner != side && !self.edges[*corner].is_border() {
+                self.corner_properties.interpolate(*corner, &[(*corner, weight), (previous_side, 1. - weight)]);
+            }
+        }
+        for corner in far {
+            if !self.edges[*corner].is_border() {
+                self.corner_properties.interpolate(*corner, &[(*corner, 1. - weight), (side, weight)]);
+            }
+        }
+
+        let mut triangles = Vec::new();
+        for side in &[edge, opposite_edge] {
+            let side = *side;
+            let previous_edge = self.get_prev_edge(side);
+            let next_edge = get_element!(self.edges[side], next);
+            self.edges[previous_edge].next = Some(next_edge);
+            if let Some(face) = self.edges[side].face {
+                self.facets[face].edge = Some(next_edge);
+            }
+            self.vertices[vertex].edge = Some(previous_edge);
+            if get_element!(self.edges[next_edge], next) == previous_edge {
+                triangles.push((previous_edge, next_edge));
+            }
+        }
+        self.discard_edge(edge);
+        self.discard_edge(opposite_edge);
+        self.discard_vertex(opposite_vertex);
+
+        // Glue the outer edges of each two-edge loop together.
+        for (previous_edge, next_edge) in triangles {
+            let outer_previous = get_element!(self.edges[previous_edge], opposite);
+            let outer_next = get_element!(self.edges[next_edge], opposite);
+            self.edges[outer_previous].opposite = Some(outer_next);
+            self.edges[outer_next].opposite = Some(outer_previous);
+
+            let apex = get_element!(self.edges[next_edge], vertex);
+            self.vertices[apex].edge = Some(outer_previous);
+            self.vertices[vertex].edge = Some(outer_next);
+
+            if let Some(face) = self.edges[previous_edge].face {
+                self.discard_facet(face);
+            }
+            self.discard_edge(previous_edge);
+            self.discard_edge(next_edge);
+        }
 
+        Ok(vertex)
     }
 }
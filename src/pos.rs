@@ -1,11 +1,17 @@
 use std::ops::{Index, Add, Sub, Mul, Div};
 use std::cmp::{PartialEq, Eq};
 
-pub trait Pos: Default + Copy + Clone + Sized + Index<usize> + Eq + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self> + Div<f32, Output = Self>{
+pub trait Pos: Default + Copy + Clone + Sized + Index<usize, Output = f32> + Eq + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self> + Div<f32, Output = Self>{
     fn dot(&self, rhs: &Self) -> f32;
 
+    /// Number of coordinates.
+    fn dimension() -> usize;
+
+    /// Builds a position from its first coordinates, missing ones being 0.
+    fn from_coordinates(coordinates: &[f32]) -> Self;
+
     fn sq_norm(&self) -> f32 { 
-        self.dot(&self)
+        self.dot(self)
     }
 
     fn norm(&self) -> f32 {
@@ -24,6 +30,17 @@ impl Pos for Pos2 {
     fn dot(&self, rhs: &Self) -> f32 {
         self.x * rhs.x + self.y * rhs.y
     }
+
+    fn dimension() -> usize {
+        2
+    }
+
+    fn from_coordinates(coordinates: &[f32]) -> Self {
+        Pos2 {
+            x: coordinates.first().cloned().unwrap_or(0.),
+            y: coordinates.get(1).cloned().unwrap_or(0.),
+        }
+    }
 }
 
 impl Index<usize> for Pos2 {
@@ -95,3 +112,113 @@ impl Mul<Pos2> for f32 {
     }
 
 }
+
+#[derive(Default, Copy, Clone)]
+pub struct Pos3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Pos3 {
+    pub fn cross(&self, rhs: &Self) -> Pos3 {
+        Pos3 {
+            x: self.y * rhs.z - self.z * rhs.y,
+            y: self.z * rhs.x - self.x * rhs.z,
+            z: self.x * rhs.y - self.y * rhs.x,
+        }
+    }
+}
+
+impl Pos for Pos3 {
+    fn dot(&self, rhs: &Self) -> f32 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    fn dimension() -> usize {
+        3
+    }
+
+    fn from_coordinates(coordinates: &[f32]) -> Self {
+        Pos3 {
+            x: coordinates.first().cloned().unwrap_or(0.),
+            y: coordinates.get(1).cloned().unwrap_or(0.),
+            z: coordinates.get(2).cloned().unwrap_or(0.),
+        }
+    }
+}
+
+impl Index<usize> for Pos3 {
+    type Output = f32;
+    fn index(&self, i: usize) -> &Self::Output {
+        match i {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Accessing position out of bounds."),
+        }
+    }
+}
+
+impl PartialEq for Pos3 {
+    fn eq(&self, other: &Pos3) -> bool {
+        (self.x == other.x) && (self.y == other.y) && (self.z == other.z)
+    }
+}
+
+impl Eq for Pos3 {}
+
+impl Add for Pos3 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        Pos3 {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl Sub for Pos3 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        Pos3 {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+impl Div<f32> for Pos3 {
+    type Output = Self;
+    fn div(self, rhs: f32) -> Self::Output {
+        Pos3 {
+            x: self.x / rhs,
+            y: self.y / rhs,
+            z: self.z / rhs,
+        }
+    }
+}
+
+impl Mul<f32> for Pos3 {
+    type Output = Self;
+    fn mul(self, rhs: f32) -> Self::Output {
+        Pos3 {
+            x: self.x * rhs,
+            y: self.y * rhs,
+            z: self.z * rhs,
+        }
+    }
+}
+
+impl Mul<Pos3> for f32 {
+    type Output = Pos3;
+    fn mul(self, rhs: Pos3) -> Self::Output {
+        Pos3 {
+            x: self * rhs.x,
+            y: self * rhs.y,
+            z: self * rhs.z,
+        }
+    }
+}
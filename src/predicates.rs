@@ -0,0 +1,223 @@
+use pos::Pos;
+
+// Exact signs of the orientation and in-circle determinants, in the adaptive
+// style of Shewchuk's predicates: the determinant is first evaluated in f64
+// with an error bound, and only when the bound does not settle its sign is it
+// evaluated again exactly.
+//
+// The exact evaluation expands the determinant into products of coordinates.
+// Coordinates are f32, so each product is exact as a sum of a few f64, and the
+// products are added up without rounding as nonoverlapping expansions. Overflow
+// and underflow are not handled.
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Sign {
+    Negative,
+    Zero,
+    Positive,
+}
+
+impl Sign {
+    pub fn reversed(self) -> Sign {
+        match self {
+            Sign::Negative => Sign::Positive,
+            Sign::Zero => Sign::Zero,
+            Sign::Positive => Sign::Negative,
+        }
+    }
+
+    fn of(value: f64) -> Sign {
+        if value > 0. {
+            Sign::Positive
+        } else if value < 0. {
+            Sign::Negative
+        } else {
+            Sign::Zero
+        }
+    }
+}
+
+const EPSILON: f64 = f64::EPSILON / 2.;
+const ORIENT2D_BOUND: f64 = (3. + 16. * EPSILON) * EPSILON;
+const ORIENT3D_BOUND: f64 = (7. + 56. * EPSILON) * EPSILON;
+const INCIRCLE_BOUND: f64 = (10. + 96. * EPSILON) * EPSILON;
+
+/// Positive when `a`, `b` and `c` turn counterclockwise, zero when they are
+/// collinear. Only the first two coordinates are used.
+pub fn orient2d<T: Pos>(a: T, b: T, c: T) -> Sign {
+    let (ax, ay) = (f64::from(a[0]), f64::from(a[1]));
+    let (bx, by) = (f64::from(b[0]), f64::from(b[1]));
+    let (cx, cy) = (f64::from(c[0]), f64::from(c[1]));
+
+    let left = (ax - cx) * (by - cy);
+    let right = (ay - cy) * (bx - cx);
+    let determinant = left - right;
+    if determinant.abs() > ORIENT2D_BOUND * (left.abs() + right.abs()) {
+        return Sign::of(determinant);
+    }
+
+    let row = |x: f64, y: f64| vec![linear(x), linear(y), constant()];
+    exact_determinant_sign(&[row(ax, ay), row(bx, by), row(cx, cy)])
+}
+
+/// Positive when `d` lies below the plane through `a`, `b` and `c`, below
+/// being the side from which `a`, `b` and `c` appear clockwise. Zero when the
+/// four points are coplanar.
+pub fn orient3d<T: Pos>(a: T, b: T, c: T, d: T) -> Sign {
+    assert!(T::dimension() >= 3, "orient3d needs three coordinates.");
+    let coordinates = |p: T| (f64::from(p[0]), f64::from(p[1]), f64::from(p[2]));
+    let (ax, ay, az) = coordinates(a);
+    let (bx, by, bz) = coordinates(b);
+    let (cx, cy, cz) = coordinates(c);
+    let (dx, dy, dz) = coordinates(d);
+
+    let (adx, ady, adz) = (ax - dx, ay - dy, az - dz);
+    let (bdx, bdy, bdz) = (bx - dx, by - dy, bz - dz);
+    let (cdx, cdy, cdz) = (cx - dx, cy - dy, cz - dz);
+    let (bdxcdy, cdxbdy) = (bdx * cdy, cdx * bdy);
+    let (cdxady, adxcdy) = (cdx * ady, adx * cdy);
+    let (adxbdy, bdxady) = (adx * bdy, bdx * ady);
+
+    let determinant = adz * (bdxcdy - cdxbdy) + bdz * (cdxady - adxcdy) + cdz * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * adz.abs()
+        + (cdxady.abs() + adxcdy.abs()) * bdz.abs()
+        + (adxbdy.abs() + bdxady.abs()) * cdz.abs();
+    if determinant.abs() > ORIENT3D_BOUND * permanent {
+        return Sign::of(determinant);
+    }
+
+    let row = |x: f64, y: f64, z: f64| vec![linear(x), linear(y), linear(z), constant()];
+    exact_determinant_sign(&[row(ax, ay, az), row(bx, by, bz), row(cx, cy, cz), row(dx, dy, dz)])
+}
+
+/// Positive when `d` lies inside the circle through `a`, `b` and `c`, given
+/// counterclockwise, and zero when the four points are cocircular. Only the
+/// first two coordinates are used.
+pub fn incircle<T: Pos>(a: T, b: T, c: T, d: T) -> Sign {
+    let (ax, ay) = (f64::from(a[0]), f64::from(a[1]));
+    let (bx, by) = (f64::from(b[0]), f64::from(b[1]));
+    let (cx, cy) = (f64::from(c[0]), f64::from(c[1]));
+    let (dx, dy) = (f64::from(d[0]), f64::from(d[1]));
+
+    let (adx, ady) = (ax - dx, ay - dy);
+    let (bdx, bdy) = (bx - dx, by - dy);
+    let (cdx, cdy) = (cx - dx, cy - dy);
+    let (bdxcdy, cdxbdy) = (bdx * cdy, cdx * bdy);
+    let (cdxady, adxcdy) = (cdx * ady, adx * cdy);
+    let (adxbdy, bdxady) = (adx * bdy, bdx * ady);
+    let a_lift = adx * adx + ady * ady;
+    let b_lift = bdx * bdx + bdy * bdy;
+    let c_lift = cdx * cdx + cdy * cdy;
+
+    let determinant = a_lift * (bdxcdy - cdxbdy) + b_lift * (cdxady - adxcdy) + c_lift * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * a_lift
+        + (cdxady.abs() + adxcdy.abs()) * b_lift
+        + (adxbdy.abs() + bdxady.abs()) * c_lift;
+    if determinant.abs() > INCIRCLE_BOUND * permanent {
+        return Sign::of(determinant);
+    }
+
+    let row = |x: f64, y: f64| vec![linear(x), linear(y), vec![(1., vec![x, x]), (1., vec![y, y])], constant()];
+    exact_determinant_sign(&[row(ax, ay), row(bx, by), row(cx, cy), row(dx, dy)])
+}
+
+/// A sum of signed products of coordinates.
+type Polynomial = Vec<(f64, Vec<f64>)>;
+
+fn linear(x: f64) -> Polynomial {
+    vec![(1., vec![x])]
+}
+
+fn constant() -> Polynomial {
+    vec![(1., Vec::new())]
+}
+
+/// Sign of the determinant of a small matrix, by adding up exactly the
+/// products of its Leibniz formula.
+fn exact_determinant_sign(rows: &[Vec<Polynomial>]) -> Sign {
+    let mut total = Vec::new();
+    for (permutation, parity) in permutations(rows.len()) {
+        let mut products: Polynomial = vec![(parity, Vec::new())];
+        for (row, &column) in rows.iter().zip(permutation.iter()) {
+            let mut expanded = Vec::with_capacity(products.len() * row[column].len());
+            for &(sign, ref factors) in &products {
+                for &(term_sign, ref term_factors) in &row[column] {
+                    let mut all_factors = factors.clone();
+                    all_factors.extend_from_slice(term_factors);
+                    expanded.push((sign * term_sign, all_factors));
+                }
+            }
+            products = expanded;
+        }
+
+        for (sign, factors) in products {
+            let mut product = vec![sign];
+            for factor in factors {
+                product = scale_expansion(&product, factor);
+            }
+            for component in product {
+                grow_expansion(&mut total, component);
+            }
+        }
+    }
+    // The largest component of a nonoverlapping expansion gives its sign.
+    total.last().map_or(Sign::Zero, |largest| Sign::of(*largest))
+}
+
+/// Permutations of `0..n` with their parity as 1 or -1.
+fn permutations(n: usize) -> Vec<(Vec<usize>, f64)> {
+    if n == 0 {
+        return vec![(Vec::new(), 1.)];
+    }
+    let mut result = Vec::new();
+    for (smaller, parity) in permutations(n - 1) {
+        // Inserting n - 1 at position i moves it past n - 1 - i elements.
+        for i in 0..n {
+            let mut permutation = smaller.clone();
+            permutation.insert(i, n - 1);
+            let swaps = n - 1 - i;
+            result.push((permutation, if swaps.is_multiple_of(2) { parity } else { -parity }));
+        }
+    }
+    result
+}
+
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    let a_virtual = sum - b_virtual;
+    (sum, (a - a_virtual) + (b - b_virtual))
+}
+
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let product = a * b;
+    (product, a.mul_add(b, -product))
+}
+
+/// Adds `value` to an expansion whose components are nonoverlapping and
+/// sorted by increasing magnitude, keeping it so and dropping zeros.
+fn grow_expansion(expansion: &mut Vec<f64>, value: f64) {
+    let mut sum = value;
+    let mut grown = Vec::with_capacity(expansion.len() + 1);
+    for &component in expansion.iter() {
+        let (new_sum, error) = two_sum(sum, component);
+        sum = new_sum;
+        if error != 0. {
+            grown.push(error);
+        }
+    }
+    if sum != 0. {
+        grown.push(sum);
+    }
+    *expansion = grown;
+}
+
+fn scale_expansion(expansion: &[f64], factor: f64) -> Vec<f64> {
+    let mut scaled = Vec::with_capacity(2 * expansion.len());
+    for &component in expansion {
+        let (product, error) = two_product(component, factor);
+        grow_expansion(&mut scaled, error);
+        grow_expansion(&mut scaled, product);
+    }
+    scaled
+}
@@ -0,0 +1,401 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use error::HalfEdgeError;
+use polyhedron::Polyhedron;
+use pos::{Pos, Pos2, Pos3};
+use utils::*;
+
+// Typed values attached to the vertices, half-edges and facets of a mesh,
+// stored by slot index next to the arenas. The value of a half-edge belongs to
+// the corner of its facet at the vertex of the half-edge, as UV coordinates do.
+//
+// Each operator sets the values of the elements it creates from those of the
+// elements they come from, and removed elements get back the default value.
+
+/// A value that can be stored in a property map.
+pub trait Property: Clone + Default + 'static {
+    /// Blends `values`, whose weights add up to 1. By default, takes the value
+    /// with the largest weight.
+    fn interpolate(values: &[(&Self, f32)]) -> Self {
+        let mut best: Option<(&Self, f32)> = None;
+        for &(value, weight) in values {
+            if best.is_none_or(|(_, best_weight)| weight > best_weight) {
+                best = Some((value, weight));
+            }
+        }
+        best.map_or_else(Self::default, |(value, _)| value.clone())
+    }
+}
+
+macro_rules! linear_property {
+    ($($t: ty),*) => {$(
+        impl Property for $t {
+            fn interpolate(values: &[(&$t, f32)]) -> $t {
+                values.iter().fold(<$t>::default(), |sum, &(value, weight)| sum + *value * weight)
+            }
+        }
+    )*};
+}
+
+macro_rules! array_property {
+    ($($n: expr),*) => {$(
+        impl Property for [f32; $n] {
+            fn interpolate(values: &[(&[f32; $n], f32)]) -> [f32; $n] {
+                let mut sum = [0.; $n];
+                for &(value, weight) in values {
+                    for k in 0..$n {
+                        sum[k] += value[k] * weight;
+                    }
+                }
+                sum
+            }
+        }
+    )*};
+}
+
+linear_property!(f32, Pos2, Pos3);
+array_property!(2, 3, 4);
+
+impl Property for f64 {
+    fn interpolate(values: &[(&f64, f32)]) -> f64 {
+        values.iter().fold(0., |sum, &(value, weight)| sum + *value * f64::from(weight))
+    }
+}
+
+/// RGBA colours.
+impl Property for [u8; 4] {
+    fn interpolate(values: &[(&[u8; 4], f32)]) -> [u8; 4] {
+        let mut sum = [0f32; 4];
+        for &(value, weight) in values {
+            for k in 0..4 {
+                sum[k] += f32::from(value[k]) * weight;
+            }
+        }
+        let mut color = [0u8; 4];
+        for k in 0..4 {
+            color[k] = sum[k].round().clamp(0., 255.) as u8;
+        }
+        color
+    }
+}
+
+impl Property for bool {}
+impl Property for i32 {}
+impl Property for i64 {}
+impl Property for u32 {}
+impl Property for u64 {}
+impl Property for usize {}
+impl Property for String {}
+
+/// Names a property map of the elements with ids `I` and values `V`.
+pub struct PropertyHandle<I, V> {
+    index: usize,
+    marker: PhantomData<(I, V)>,
+}
+
+impl<I, V> Clone for PropertyHandle<I, V> {
+    fn clone(&self) -> PropertyHandle<I, V> {
+        *self
+    }
+}
+
+impl<I, V> Copy for PropertyHandle<I, V> {}
+
+impl<I, V> PartialEq for PropertyHandle<I, V> {
+    fn eq(&self, other: &PropertyHandle<I, V>) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<I, V> Eq for PropertyHandle<I, V> {}
+
+trait PropertyMap {
+    fn name(&self) -> &str;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn value(&self, index: usize) -> Box<dyn Any>;
+    fn replace_value(&mut self, index: usize, value: Box<dyn Any>) -> Box<dyn Any>;
+    fn reset(&mut self, index: usize);
+    fn interpolate(&mut self, target: usize, sources: &[(usize, f32)]);
+    fn remapped(&self, pairs: &[(usize, usize)]) -> Box<dyn PropertyMap>;
+}
+
+struct TypedMap<V> {
+    name: String,
+    values: Vec<V>,
+    default: V,
+}
+
+impl<V: Property> TypedMap<V> {
+    fn get(&self, index: usize) -> &V {
+        self.values.get(index).unwrap_or(&self.default)
+    }
+
+    fn get_mut(&mut self, index: usize) -> &mut V {
+        if index >= self.values.len() {
+            self.values.resize(index + 1, V::default());
+        }
+        &mut self.values[index]
+    }
+}
+
+impl<V: Property> PropertyMap for TypedMap<V> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn value(&self, index: usize) -> Box<dyn Any> {
+        Box::new(self.get(index).clone())
+    }
+
+    fn replace_value(&mut self, index: usize, value: Box<dyn Any>) -> Box<dyn Any> {
+        let value = *value.downcast::<V>().unwrap_or_else(|_| panic!("Wrong value type for property map '{}'.", self.name));
+        Box::new(::std::mem::replace(self.get_mut(index), value))
+    }
+
+    fn reset(&mut self, index: usize) {
+        if index < self.values.len() {
+            self.values[index] = V::default();
+        }
+    }
+
+    fn interpolate(&mut self, target: usize, sources: &[(usize, f32)]) {
+        let value = {
+            let values: Vec<(&V, f32)> = sources.iter().map(|&(index, weight)| (self.get(index), weight)).collect();
+            V::interpolate(&values)
+        };
+        *self.get_mut(target) = value;
+    }
+
+    fn remapped(&self, pairs: &[(usize, usize)]) -> Box<dyn PropertyMap> {
+        let mut map = TypedMap { name: self.name.clone(), values: Vec::new(), default: V::default() };
+        for &(old, new) in pairs {
+            *map.get_mut(new) = self.get(old).clone();
+        }
+        Box::new(map)
+    }
+}
+
+/// The property maps of one kind of element.
+///
+/// Like the arenas, the maps keep the previous value of every slot written
+/// while their journal is open.
+pub struct PropertyMaps<I: ElementId> {
+    maps: Vec<Option<Box<dyn PropertyMap>>>,
+    journal: Option<HashMap<(usize, usize), Box<dyn Any>>>,
+    marker: PhantomData<I>,
+}
+
+impl<I: ElementId> PropertyMaps<I> {
+    pub(crate) fn new() -> PropertyMaps<I> {
+        PropertyMaps { maps: Vec::new(), journal: None, marker: PhantomData }
+    }
+
+    fn typed<V: Property>(&self, handle: PropertyHandle<I, V>) -> &TypedMap<V> {
+        self.maps.get(handle.index)
+            .and_then(|map| map.as_ref())
+            .and_then(|map| map.as_any().downcast_ref::<TypedMap<V>>())
+            .unwrap_or_else(|| panic!("Accessing removed property map {}.", handle.index))
+    }
+
+    fn typed_mut<V: Property>(&mut self, handle: PropertyHandle<I, V>) -> &mut TypedMap<V> {
+        self.maps.get_mut(handle.index)
+            .and_then(|map| map.as_mut())
+            .and_then(|map| map.as_any_mut().downcast_mut::<TypedMap<V>>())
+            .unwrap_or_else(|| panic!("Accessing removed property map {}.", handle.index))
+    }
+
+    fn find<V: Property>(&self, name: &str) -> Option<PropertyHandle<I, V>> {
+        self.maps.iter()
+            .position(|map| map.as_ref().is_some_and(|map| map.name() == name && map.as_any().is::<TypedMap<V>>()))
+            .map(|index| PropertyHandle { index, marker: PhantomData })
+    }
+
+    fn add<V: Property>(&mut self, name: &str) -> PropertyHandle<I, V> {
+        if let Some(handle) = self.find(name) {
+            return handle;
+        }
+        // Indices of removed maps are not reused, so old handles stay invalid.
+        self.maps.push(Some(Box::new(TypedMap { name: name.to_string(), values: Vec::new(), default: V::default() })));
+        PropertyHandle { index: self.maps.len() - 1, marker: PhantomData }
+    }
+
+    fn remove<V: Property>(&mut self, handle: PropertyHandle<I, V>) {
+        if let Some(map) = self.maps.get_mut(handle.index) {
+            *map = None;
+        }
+    }
+
+    fn touch(&mut self, map: usize, index: usize) {
+        if let Some(ref mut journal) = self.journal {
+            if let Some(Some(ref property_map)) = self.maps.get(map) {
+                journal.entry((map, index)).or_insert_with(|| property_map.value(index));
+            }
+        }
+    }
+
+    /// Gives back the default value to every map at `id`.
+    pub(crate) fn reset(&mut self, id: I) {
+        for map in 0..self.maps.len() {
+            self.touch(map, id.index());
+            if let Some(Some(ref mut property_map)) = self.maps.get_mut(map) {
+                property_map.reset(id.index());
+            }
+        }
+    }
+
+    /// Sets the value at `target` to the blend of the values at `sources`, in
+    /// every map.
+    pub(crate) fn interpolate(&mut self, target: I, sources: &[(I, f32)]) {
+        let sources: Vec<(usize, f32)> = sources.iter().map(|&(id, weight)| (id.index(), weight)).collect();
+        for map in 0..self.maps.len() {
+            self.touch(map, target.index());
+            if let Some(Some(ref mut property_map)) = self.maps.get_mut(map) {
+                property_map.interpolate(target.index(), &sources);
+            }
+        }
+    }
+
+    pub(crate) fn open_journal(&mut self) {
+        self.journal = Some(HashMap::new());
+    }
+
+    pub(crate) fn close_journal(&mut self) -> Vec<((usize, usize), Box<dyn Any>)> {
+        let mut entries: Vec<_> = self.journal.take().map_or_else(Vec::new, |journal| journal.into_iter().collect());
+        entries.sort_by_key(|&(key, _)| key);
+        entries
+    }
+
+    pub(crate) fn resume_journal(&mut self, entries: Vec<((usize, usize), Box<dyn Any>)>) {
+        self.journal = Some(entries.into_iter().collect());
+    }
+
+    /// Puts back a value taken from the journal, returning the one it replaced.
+    /// Values of removed maps are returned as they are.
+    pub(crate) fn replace_value(&mut self, key: (usize, usize), value: Box<dyn Any>) -> Box<dyn Any> {
+        self.touch(key.0, key.1);
+        match self.maps.get_mut(key.0) {
+            Some(&mut Some(ref mut property_map)) => property_map.replace_value(key.1, value),
+            _ => value,
+        }
+    }
+
+    /// Copies the maps, moving the value at each old index to its new index.
+    pub(crate) fn remapped(&self, map: &HashMap<I, I>) -> PropertyMaps<I> where I: ::std::hash::Hash {
+        let pairs: Vec<(usize, usize)> = map.iter().map(|(old, new)| (old.index(), new.index())).collect();
+        PropertyMaps {
+            maps: self.maps.iter().map(|property_map| property_map.as_ref().map(|property_map| property_map.remapped(&pairs))).collect(),
+            journal: None,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Ids of the elements that can have properties.
+pub trait PropertyElement: ElementId + 'static {
+    fn property_maps<T: Pos>(poly: &Polyhedron<T>) -> &PropertyMaps<Self>;
+    fn property_maps_mut<T: Pos>(poly: &mut Polyhedron<T>) -> &mut PropertyMaps<Self>;
+}
+
+impl PropertyElement for VertexId {
+    fn property_maps<T: Pos>(poly: &Polyhedron<T>) -> &PropertyMaps<VertexId> {
+        &poly.vertex_properties
+    }
+
+    fn property_maps_mut<T: Pos>(poly: &mut Polyhedron<T>) -> &mut PropertyMaps<VertexId> {
+        &mut poly.vertex_properties
+    }
+}
+
+impl PropertyElement for HalfEdgeId {
+    fn property_maps<T: Pos>(poly: &Polyhedron<T>) -> &PropertyMaps<HalfEdgeId> {
+        &poly.corner_properties
+    }
+
+    fn property_maps_mut<T: Pos>(poly: &mut Polyhedron<T>) -> &mut PropertyMaps<HalfEdgeId> {
+        &mut poly.corner_properties
+    }
+}
+
+impl PropertyElement for FacetId {
+    fn property_maps<T: Pos>(poly: &Polyhedron<T>) -> &PropertyMaps<FacetId> {
+        &poly.facet_properties
+    }
+
+    fn property_maps_mut<T: Pos>(poly: &mut Polyhedron<T>) -> &mut PropertyMaps<FacetId> {
+        &mut poly.facet_properties
+    }
+}
+
+impl<T: Pos> Polyhedron<T> {
+    /// Adds a property map of the vertices, or returns the map with the same
+    /// name and type if there is one. Vertices start with the default value.
+    pub fn add_vertex_property<V: Property>(&mut self, name: &str) -> PropertyHandle<VertexId, V> {
+        self.vertex_properties.add(name)
+    }
+
+    /// Adds a property map of the corners of the facets, stored on the
+    /// half-edge ending at each corner.
+    pub fn add_corner_property<V: Property>(&mut self, name: &str) -> PropertyHandle<HalfEdgeId, V> {
+        self.corner_properties.add(name)
+    }
+
+    pub fn add_facet_property<V: Property>(&mut self, name: &str) -> PropertyHandle<FacetId, V> {
+        self.facet_properties.add(name)
+    }
+
+    pub fn find_property<I: PropertyElement, V: Property>(&self, name: &str) -> Option<PropertyHandle<I, V>> {
+        I::property_maps(self).find(name)
+    }
+
+    pub fn remove_property<I: PropertyElement, V: Property>(&mut self, handle: PropertyHandle<I, V>) {
+        I::property_maps_mut(self).remove(handle)
+    }
+
+    pub fn property<I: PropertyElement, V: Property>(&self, handle: PropertyHandle<I, V>, id: I) -> &V {
+        I::property_maps(self).typed(handle).get(id.index())
+    }
+
+    /// Changes made through the reference are not recorded for `undo`, unlike
+    /// those of `set_property`.
+    pub fn property_mut<I: PropertyElement, V: Property>(&mut self, handle: PropertyHandle<I, V>, id: I) -> &mut V {
+        let maps = I::property_maps_mut(self);
+        maps.touch(handle.index, id.index());
+        maps.typed_mut(handle).get_mut(id.index())
+    }
+
+    pub fn set_property<I: PropertyElement, V: Property>(&mut self, handle: PropertyHandle<I, V>, id: I, value: V) {
+        let result: Result<(), HalfEdgeError> = self.record(|poly| {
+            *poly.property_mut(handle, id) = value;
+            Ok(())
+        });
+        result.unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    // Removes an element and gives back the default value to its properties,
+    // so that a new element taking its slot starts from the default.
+    pub(crate) fn discard_vertex(&mut self, vertex: VertexId) {
+        self.vertices.remove(vertex);
+        self.vertex_properties.reset(vertex);
+    }
+
+    pub(crate) fn discard_edge(&mut self, edge: HalfEdgeId) {
+        self.edges.remove(edge);
+        self.corner_properties.reset(edge);
+    }
+
+    pub(crate) fn discard_facet(&mut self, facet: FacetId) {
+        self.facets.remove(facet);
+        self.facet_properties.reset(facet);
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+use obj::invalid_data;
+use polyhedron::Polyhedron3;
+use pos::{Pos, Pos2, Pos3};
+use predicates::{orient2d, Sign};
+
+// STL files store each triangle with its own copy of its corners, so the
+// reader welds corners closer than a tolerance into shared vertices.
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StlFormat {
+    Ascii,
+    Binary,
+}
+
+/// Merges points closer than `tolerance`, returning the merged points and
+/// the index of the merged point for each input point.
+fn weld(points: &[Pos3], tolerance: f32) -> (Vec<Pos3>, Vec<usize>) {
+    let mut welded: Vec<Pos3> = Vec::new();
+    let mut indices = Vec::with_capacity(points.len());
+
+    if tolerance <= 0. {
+        let mut exact = HashMap::new();
+        for p in points {
+            let key = (p.x.to_bits(), p.y.to_bits(), p.z.to_bits());
+            let index = *exact.entry(key).or_insert_with(|| {
+                welded.push(*p);
+                welded.len() - 1
+            });
+            indices.push(index);
+        }
+        return (welded, indices);
+    }
+
+    // Points within the tolerance are at most one grid cell apart.
+    let cell = |p: &Pos3| ((p.x / tolerance).floor() as i64, (p.y / tolerance).floor() as i64, (p.z / tolerance).floor() as i64);
+    let mut grid: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+    for p in points {
+        let (x, y, z) = cell(p);
+        let mut found = None;
+        'search: for dx in -1..2 {
+            for dy in -1..2 {
+                for dz in -1..2 {
+                    for &index in grid.get(&(x + dx, y + dy, z + dz)).map_or(&[][..], |cell| &cell[..]) {
+                        if (welded[index] - *p).norm() <= tolerance {
+                            found = Some(index);
+                            break 'search;
+                        }
+                    }
+                }
+            }
+        }
+        let index = found.unwrap_or_else(|| {
+            welded.push(*p);
+            grid.entry((x, y, z)).or_default().push(welded.len() - 1);
+            welded.len() - 1
+        });
+        indices.push(index);
+    }
+    (welded, indices)
+}
+
+/// The triangle count of a binary file, if the file is long enough to hold
+/// that many triangles.
+fn binary_count(bytes: &[u8]) -> Option<usize> {
+    if bytes.len() < 84 {
+        return None;
+    }
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    if bytes.len() >= 84 + 50 * count { Some(count) } else { None }
+}
+
+fn read_binary_triangles(bytes: &[u8]) -> io::Result<Vec<Pos3>> {
+    let count = binary_count(bytes).ok_or_else(|| invalid_data("binary STL file too short"))?;
+    let mut corners = Vec::with_capacity(3 * count);
+    for triangle in bytes[84..].chunks(50).take(count) {
+        // The normal is recomputed from the corners, so skip it.
+        for corner in 1..4 {
+            let mut coordinates = [0f32; 3];
+            for (k, coordinate) in coordinates.iter_mut().enumerate() {
+                let offset = 12 * corner + 4 * k;
+                *coordinate = f32::from_le_bytes([triangle[offset], triangle[offset + 1], triangle[offset + 2], triangle[offset + 3]]);
+            }
+            corners.push(Pos3::from_coordinates(&coordinates));
+        }
+    }
+    Ok(corners)
+}
+
+fn read_ascii_triangles(text: &str) -> io::Result<Vec<Pos3>> {
+    let mut corners = Vec::new();
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("vertex") {
+            continue;
+        }
+        let coordinates = tokens
+            .map(|token| token.parse::<f32>().map_err(|_| invalid_data(format!("invalid coordinate '{}'", token))))
+            .collect::<io::Result<Vec<f32>>>()?;
+        if coordinates.len() != 3 {
+            return Err(invalid_data(format!("invalid vertex line '{}'", line.trim())));
+        }
+        corners.push(Pos3::from_coordinates(&coordinates));
+    }
+    if corners.len() % 3 != 0 {
+        return Err(invalid_data("STL facet without three vertices"));
+    }
+    Ok(corners)
+}
+
+fn normal(a: Pos3, b: Pos3, c: Pos3) -> Pos3 {
+    let normal = (b - a).cross(&(c - a));
+    let norm = normal.norm();
+    if norm > 0. { normal / norm } else { normal }
+}
+
+/// Splits a simple polygon into triangles by clipping ears, seen along the
+/// largest coordinate of its normal. What is left when no ear is found, as in
+/// degenerate polygons, is split into a fan.
+fn triangulate(corners: &[Pos3]) -> Vec<[usize; 3]> {
+    let n = corners.len();
+    let mut normal = [0f64; 3];
+    for i in 0..n {
+        let (p, q) = (corners[i], corners[(i + 1) % n]);
+        for (k, coordinate) in normal.iter_mut().enumerate() {
+            let (u, v) = ((k + 1) % 3, (k + 2) % 3);
+            *coordinate += (f64::from(p[u]) - f64::from(q[u])) * (f64::from(p[v]) + f64::from(q[v]));
+        }
+    }
+    let axis = (0..3).fold(0, |best, k| if normal[k].abs() > normal[best].abs() { k } else { best });
+    let points: Vec<Pos2> = corners.iter().map(|p| Pos2 { x: p[(axis + 1) % 3], y: p[(axis + 2) % 3] }).collect();
+    let turn = if normal[axis] >= 0. { Sign::Positive } else { Sign::Negative };
+    let outside = |p: Pos2, a: Pos2, b: Pos2, c: Pos2| {
+        let back = turn.reversed();
+        orient2d(a, b, p) == back || orient2d(b, c, p) == back || orient2d(c, a, p) == back
+    };
+
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles = Vec::with_capacity(n.saturating_sub(2));
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let ear = (0..m).find(|&i| {
+            let (a, b, c) = (remaining[(i + m - 1) % m], remaining[i], remaining[(i + 1) % m]);
+            orient2d(points[a], points[b], points[c]) == turn && remaining.iter()
+                .filter(|&&j| j != a && j != b && j != c)
+                .all(|&j| outside(points[j], points[a], points[b], points[c]))
+        });
+        match ear {
+            Some(i) => {
+                triangles.push([remaining[(i + m - 1) % m], remaining[i], remaining[(i + 1) % m]]);
+                remaining.remove(i);
+            }
+            None => break,
+        }
+    }
+    for k in 1..remaining.len().saturating_sub(1) {
+        triangles.push([remaining[0], remaining[k], remaining[k + 1]]);
+    }
+    triangles
+}
+
+impl Polyhedron3 {
+    /// Reads an ASCII or binary STL mesh, merging corners closer than `tolerance`.
+    /// Triangles that lose a corner in the merge are dropped, and so are
+    /// triangles merged into one already read, whatever their orientation.
+    pub fn read_stl<R: Read>(mut reader: R, tolerance: f32) -> io::Result<Polyhedron3> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        // Binary files may also start with "solid", so trust an exact size
+        // first. Otherwise a "solid" file that gives no vertex is read as
+        // binary if it is long enough for that, as some exporters pad their
+        // binary files, and is only empty if it ends like an ASCII file.
+        let count = binary_count(&bytes);
+        let corners = if count.is_some_and(|count| bytes.len() == 84 + 50 * count) {
+            read_binary_triangles(&bytes)?
+        } else if bytes.starts_with(b"solid") {
+            let text = String::from_utf8_lossy(&bytes);
+            match read_ascii_triangles(&text) {
+                Ok(ref corners) if corners.is_empty() && count.is_some_and(|count| count > 0) => read_binary_triangles(&bytes)?,
+                Ok(ref corners) if corners.is_empty() && !text.lines().any(|line| line.trim_start().starts_with("endsolid")) => {
+                    return Err(invalid_data("no triangle found in STL file"));
+                }
+                Err(_) if count.is_some() => read_binary_triangles(&bytes)?,
+                result => result?,
+            }
+        } else if count.is_some() {
+            read_binary_triangles(&bytes)?
+        } else {
+            return Err(invalid_data("not an STL file"));
+        };
+
+        let (positions, indices) = weld(&corners, tolerance);
+        let mut seen = HashSet::new();
+        let faces: Vec<Vec<usize>> = indices.chunks(3)
+            .filter(|t| t[0] != t[1] && t[1] != t[2] && t[2] != t[0])
+            .filter(|t| {
+                let mut key = [t[0], t[1], t[2]];
+                key.sort();
+                seen.insert(key)
+            })
+            .map(|t| t.to_vec())
+            .collect();
+        Polyhedron3::from_faces(&positions, &faces).map_err(invalid_data)
+    }
+
+    pub fn load_stl(path: &str, tolerance: f32) -> io::Result<Polyhedron3> {
+        Polyhedron3::read_stl(BufReader::new(File::open(path)?), tolerance)
+    }
+
+    /// Writes the mesh as STL, splitting each facet into triangles by ear
+    /// clipping, so that simple non-convex facets are kept. Rings of facets
+    /// are not written.
+    pub fn write_stl<W: Write>(&self, writer: &mut W, format: StlFormat) -> io::Result<()> {
+        let mut triangles = Vec::new();
+        for facet in self.facets.ids() {
+            let corners: Vec<Pos3> = self.facet_vertices(facet).map(|vertex| self.vertices[vertex].position).collect();
+            for t in triangulate(&corners) {
+                triangles.push([corners[t[0]], corners[t[1]], corners[t[2]]]);
+            }
+        }
+
+        match format {
+            StlFormat::Ascii => {
+                writeln!(writer, "solid mesh")?;
+                for t in &triangles {
+                    let n = normal(t[0], t[1], t[2]);
+                    writeln!(writer, "facet normal {} {} {}", n.x, n.y, n.z)?;
+                    writeln!(writer, "outer loop")?;
+                    for p in t {
+                        writeln!(writer, "vertex {} {} {}", p.x, p.y, p.z)?;
+                    }
+                    writeln!(writer, "endloop")?;
+                    writeln!(writer, "endfacet")?;
+                }
+                writeln!(writer, "endsolid mesh")
+            }
+            StlFormat::Binary => {
+                writer.write_all(&[0u8; 80])?;
+                writer.write_all(&(triangles.len() as u32).to_le_bytes())?;
+                for t in &triangles {
+                    let n = normal(t[0], t[1], t[2]);
+                    for p in Some(n).iter().chain(t.iter()) {
+                        for k in 0..3 {
+                            writer.write_all(&p[k].to_le_bytes())?;
+                        }
+                    }
+                    writer.write_all(&[0u8; 2])?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn save_stl(&self, path: &str, format: StlFormat) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_stl(&mut writer, format)?;
+        writer.flush()
+    }
+}
@@ -1,18 +1,53 @@
-use std::rc::Rc;
-use std::cell::RefCell;
+use std::fmt;
 
-pub type Handle<T> = Rc<RefCell<T>>;
+pub trait ElementId: Copy + Eq {
+    fn new(index: usize) -> Self;
+    fn index(&self) -> usize;
+}
+
+macro_rules! element_id {
+    ($name: ident) => {
+        #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(u32);
+
+        impl ElementId for $name {
+            fn new(index: usize) -> $name {
+                assert!(index < u32::MAX as usize, "Too many elements for {}.", stringify!($name));
+                $name(index as u32)
+            }
 
-pub fn new_handle<T>(object: T) -> Handle<T> {
-    Rc::new(RefCell::new(object))
+            fn index(&self) -> usize {
+                self.0 as usize
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self.0)
+            }
+        }
+    };
 }
 
+element_id!(VertexId);
+element_id!(HalfEdgeId);
+element_id!(FacetId);
+
 macro_rules! get_element {
-    ($parent: ident, $attribute: ident) => {{
-        let r = $parent.borrow();
-        match r.$attribute {
-            Some(ref o) => Rc::clone(o),
+    ($parent: expr, $attribute: ident) => {{
+        match $parent.$attribute {
+            Some(o) => o,
             None => panic!("Error: Option returned None when trying to get attribute {} from object {}!", stringify!($attribute), stringify!($parent)),
         }
     }};
 }
+
+macro_rules! try_element {
+    ($arena: expr, $id: expr, $attribute: ident, $error: ident) => {{
+        let id = $id;
+        match $arena.get(id) {
+            Some(element) => element.$attribute.ok_or($crate::error::HalfEdgeError::$error(From::from(id))),
+            None => Err($crate::error::HalfEdgeError::RemovedElement(From::from(id))),
+        }
+    }};
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+
+use error::Element;
+use polyhedron::Polyhedron;
+use pos::Pos;
+use utils::*;
+
+/// A broken invariant of the half-edge structure.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// `from` refers to an element that has been removed from the mesh.
+    DanglingReference { from: Element, to: Element },
+    MissingNext(HalfEdgeId),
+    MissingVertex(HalfEdgeId),
+    /// Every edge has a twin, border edges included.
+    MissingOpposite(HalfEdgeId),
+    /// A vertex or a facet has no associated half-edge.
+    MissingEdge(Element),
+    /// No edge has this one as `next`, so its cycle does not close.
+    OpenCycle(HalfEdgeId),
+    /// Two edges share the same `next`.
+    SharedNext { next: HalfEdgeId, first: HalfEdgeId, second: HalfEdgeId },
+    /// `opposite` is the edge itself, or its `opposite` is another edge.
+    AsymmetricOpposite(HalfEdgeId),
+    /// The edge and its opposite do not join the same two vertices.
+    OppositeEndpoints(HalfEdgeId),
+    /// The edge has a different face than the previous edge of its cycle.
+    FaceMismatch(HalfEdgeId),
+    /// The facet's edge, or the edge of one of its rings, does not lie on this facet.
+    FacetEdgeMismatch(FacetId),
+    /// A loop of the facet is reached neither by its edge nor by its rings.
+    UnlistedLoop(HalfEdgeId),
+    /// The vertex's edge does not end at this vertex.
+    VertexEdgeMismatch(VertexId),
+    /// A vertex that no edge ends at, or a facet that no edge lies on.
+    Unreferenced(Element),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::DanglingReference { from, to } => write!(f, "{:?} refers to removed {:?}", from, to),
+            Violation::MissingNext(edge) => write!(f, "{:?} has no next edge", edge),
+            Violation::MissingVertex(edge) => write!(f, "{:?} has no vertex", edge),
+            Violation::MissingOpposite(edge) => write!(f, "{:?} has no opposite edge", edge),
+            Violation::MissingEdge(element) => write!(f, "{:?} has no associated edge", element),
+            Violation::OpenCycle(edge) => write!(f, "{:?} is not the next edge of any edge", edge),
+            Violation::SharedNext { next, first, second } => write!(f, "{:?} is the next edge of both {:?} and {:?}", next, first, second),
+            Violation::AsymmetricOpposite(edge) => write!(f, "the opposite of {:?} does not point back to it", edge),
+            Violation::OppositeEndpoints(edge) => write!(f, "{:?} and its opposite do not join the same vertices", edge),
+            Violation::FaceMismatch(edge) => write!(f, "{:?} has a different face than the rest of its cycle", edge),
+            Violation::FacetEdgeMismatch(facet) => write!(f, "the edge of {:?} does not lie on it", facet),
+            Violation::UnlistedLoop(edge) => write!(f, "the loop of {:?} is not listed by its facet", edge),
+            Violation::VertexEdgeMismatch(vertex) => write!(f, "the edge of {:?} does not end at it", vertex),
+            Violation::Unreferenced(element) => write!(f, "{:?} is not referenced by any edge", element),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_valid() {
+            return write!(f, "valid mesh");
+        }
+        writeln!(f, "{} broken invariants:", self.violations.len())?;
+        for violation in &self.violations {
+            writeln!(f, "  {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Pos> Polyhedron<T> {
+    /// Walks every element of the mesh and reports each broken invariant.
+    ///
+    /// Vertices and facets with no edge at all, as left by
+    /// `make_vertex_facet_shell`, are valid.
+    pub fn validate(&self) -> ValidationReport {
+        let mut violations = Vec::new();
+
+        let mut previous: Vec<Option<HalfEdgeId>> = vec![None; self.edges.capacity()];
+        let mut vertex_referenced = vec![false; self.vertices.capacity()];
+        let mut facet_referenced = vec![false; self.facets.capacity()];
+
+        // References held by each half-edge.
+        for (id, edge) in self.edges.iter() {
+            match edge.next {
+                Some(next) if !self.edges.contains(next) => {
+                    violations.push(Violation::DanglingReference { from: id.into(), to: next.into() });
+                }
+                Some(next) => match previous[next.index()] {
+                    Some(first) => violations.push(Violation::SharedNext { next, first, second: id }),
+                    None => previous[next.index()] = Some(id),
+                },
+                None => violations.push(Violation::MissingNext(id)),
+            }
+            match edge.vertex {
+                Some(vertex) if !self.vertices.contains(vertex) => {
+                    violations.push(Violation::DanglingReference { from: id.into(), to: vertex.into() });
+                }
+                Some(vertex) => vertex_referenced[vertex.index()] = true,
+                None => violations.push(Violation::MissingVertex(id)),
+            }
+            match edge.face {
+                Some(face) if !self.facets.contains(face) => {
+                    violations.push(Violation::DanglingReference { from: id.into(), to: face.into() });
+                }
+                Some(face) => facet_referenced[face.index()] = true,
+                None => {}
+            }
+            match edge.opposite {
+                Some(opposite) if !self.edges.contains(opposite) => {
+                    violations.push(Violation::DanglingReference { from: id.into(), to: opposite.into() });
+                }
+                Some(opposite) => if opposite == id || self.edges[opposite].opposite != Some(id) {
+                    violations.push(Violation::AsymmetricOpposite(id));
+                },
+                None => violations.push(Violation::MissingOpposite(id)),
+            }
+        }
+
+        // Cycles, faces along them and the endpoints of opposite pairs.
+        for (id, edge) in self.edges.iter() {
+            let previous_edge = match previous[id.index()] {
+                Some(previous_edge) => previous_edge,
+                None => {
+                    violations.push(Violation::OpenCycle(id));
+                    continue;
+                }
+            };
+            if self.edges[previous_edge].face != edge.face {
+                violations.push(Violation::FaceMismatch(id));
+            }
+            if let Some(opposite) = edge.opposite {
+                let opposite_previous = match self.edges.get(opposite) {
+                    Some(_) => previous[opposite.index()],
+                    None => None,
+                };
+                if let Some(opposite_previous) = opposite_previous {
+                    let origin = self.edges[previous_edge].vertex;
+                    let opposite_origin = self.edges[opposite_previous].vertex;
+                    if origin != self.edges[opposite].vertex || opposite_origin != edge.vertex {
+                        violations.push(Violation::OppositeEndpoints(id));
+                    }
+                }
+            }
+        }
+
+        for (id, vertex) in self.vertices.iter() {
+            match vertex.edge {
+                Some(edge) => match self.edges.get(edge) {
+                    Some(e) => if e.vertex != Some(id) {
+                        violations.push(Violation::VertexEdgeMismatch(id));
+                    },
+                    None => violations.push(Violation::DanglingReference { from: id.into(), to: edge.into() }),
+                },
+                None => if vertex_referenced[id.index()] {
+                    violations.push(Violation::MissingEdge(id.into()));
+                },
+            }
+            if vertex.edge.is_some() && !vertex_referenced[id.index()] {
+                violations.push(Violation::Unreferenced(id.into()));
+            }
+        }
+
+        for (id, facet) in self.facets.iter() {
+            match facet.edge {
+                Some(edge) => match self.edges.get(edge) {
+                    Some(e) => if e.face != Some(id) {
+                        violations.push(Violation::FacetEdgeMismatch(id));
+                    },
+                    None => violations.push(Violation::DanglingReference { from: id.into(), to: edge.into() }),
+                },
+                None => if facet_referenced[id.index()] {
+                    violations.push(Violation::MissingEdge(id.into()));
+                },
+            }
+            if facet.edge.is_some() && !facet_referenced[id.index()] {
+                violations.push(Violation::Unreferenced(id.into()));
+            }
+            for &ring in &facet.rings {
+                match self.edges.get(ring) {
+                    Some(e) => if e.face != Some(id) {
+                        violations.push(Violation::FacetEdgeMismatch(id));
+                    },
+                    None => violations.push(Violation::DanglingReference { from: id.into(), to: ring.into() }),
+                }
+            }
+        }
+
+        // Every loop of a facet is listed by it. Loops are only walked when
+        // their cycles are sound.
+        if violations.is_empty() {
+            let mut listed = vec![false; self.edges.capacity()];
+            for (_, facet) in self.facets.iter() {
+                for start in facet.edge.iter().chain(facet.rings.iter()) {
+                    for edge in self.loop_halfedges(*start) {
+                        listed[edge.index()] = true;
+                    }
+                }
+            }
+            for (id, edge) in self.edges.iter() {
+                if edge.face.is_some() && !listed[id.index()] {
+                    violations.push(Violation::UnlistedLoop(id));
+                    for other in self.loop_halfedges(id) {
+                        listed[other.index()] = true;
+                    }
+                }
+            }
+        }
+
+        ValidationReport { violations }
+    }
+}